@@ -1,6 +1,5 @@
-use bindings::exports::wit::crossroads::router::{Guest as Router, Request};
+use bindings::exports::wit::crossroads::router::{Error, Guest as Router, Outcome, Request};
 
-use url::Url;
 use uuid::Uuid;
 #[allow(warnings)]
 mod bindings;
@@ -8,21 +7,20 @@ mod bindings;
 struct Component;
 
 impl Router for Component {
-    fn handle(request: Request) -> Result<(), String> {
-        let uri = request.uri()?;
-        let mut url = Url::parse(uri.as_str()).map_err(|e| format!("Invalid URL: {}", e))?;
-        let host = url.host_str().ok_or("Uri has no host found")?;
+    fn handle(request: &Request) -> Result<Outcome, Error> {
+        let host = request
+            .host()?
+            .ok_or_else(|| Error::InvalidUri("Uri has no host found".to_string()))?;
         let mut host_iter = host.split('.');
         let Some(uuid) = host_iter.next() else {
-            return Err("Invalid host, no subdomain".to_string());
+            return Err(Error::Rejected(404));
         };
-        Uuid::parse_str(uuid).map_err(|e| format!("Invalid subdomainUUID: {}", e))?;
-        request.set_header("UUID", uuid)?;
+        Uuid::parse_str(uuid).map_err(|_| Error::Rejected(404))?;
+        request.set_header("UUID", uuid.as_bytes())?;
         let new_host = host_iter.collect::<Vec<&str>>().join(".");
-        request.set_header("HOST", &new_host)?;
-        url.set_host(Some(&new_host))
-            .map_err(|e| format!("Invalid new host: {}", e))?;
-        request.set_uri(url.as_str())
+        request.set_header("HOST", new_host.as_bytes())?;
+        request.set_host(&new_host)?;
+        Ok(Outcome::Continue)
     }
 }
 
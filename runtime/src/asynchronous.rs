@@ -0,0 +1,255 @@
+//! Bindings for calling routers on Tokio. Their host functions are async, so
+//! reading a body that has not arrived yet waits without blocking the
+//! executor. Everything else is done by the synchronous host functions.
+
+use std::future::poll_fn;
+
+use wasmtime::component::Resource;
+use wasmtime_wasi::p2::DynInputStream;
+
+use crate::wit::crossroads::types::{
+    Error as SyncError, HostRequest as SyncHostRequest, HostResponse as SyncHostResponse,
+    Outcome as SyncOutcome,
+};
+use crate::{ComponentRunStates, Request, Response};
+
+wasmtime::component::bindgen!({
+    path: "../wit/",
+    world: "crossroads",
+    async: true,
+    require_store_data_send: true,
+    trappable_imports: ["[constructor]response", "[method]request.body"],
+    with: {
+        "wasi:io": wasmtime_wasi::p2::bindings::io,
+        "wit:crossroads/types/request": crate::Request,
+        "wit:crossroads/types/response": crate::Response,
+    }
+});
+
+use wit::crossroads::types::{Error, Host, HostRequest, HostResponse, Outcome};
+
+impl From<SyncError> for Error {
+    fn from(error: SyncError) -> Self {
+        match error {
+            SyncError::InvalidUri(message) => Self::InvalidUri(message),
+            SyncError::InvalidMethod(message) => Self::InvalidMethod(message),
+            SyncError::InvalidHeaderName(message) => Self::InvalidHeaderName(message),
+            SyncError::InvalidHeaderValue(message) => Self::InvalidHeaderValue(message),
+            SyncError::InvalidStatus(message) => Self::InvalidStatus(message),
+            SyncError::InvalidBody(message) => Self::InvalidBody(message),
+            SyncError::NotFound(message) => Self::NotFound(message),
+            SyncError::Rejected(status) => Self::Rejected(status),
+            SyncError::QuotaExceeded(message) => Self::QuotaExceeded(message),
+            SyncError::Internal(message) => Self::Internal(message),
+        }
+    }
+}
+
+impl From<Error> for SyncError {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::InvalidUri(message) => Self::InvalidUri(message),
+            Error::InvalidMethod(message) => Self::InvalidMethod(message),
+            Error::InvalidHeaderName(message) => Self::InvalidHeaderName(message),
+            Error::InvalidHeaderValue(message) => Self::InvalidHeaderValue(message),
+            Error::InvalidStatus(message) => Self::InvalidStatus(message),
+            Error::InvalidBody(message) => Self::InvalidBody(message),
+            Error::NotFound(message) => Self::NotFound(message),
+            Error::Rejected(status) => Self::Rejected(status),
+            Error::QuotaExceeded(message) => Self::QuotaExceeded(message),
+            Error::Internal(message) => Self::Internal(message),
+        }
+    }
+}
+
+impl From<Outcome> for SyncOutcome {
+    fn from(outcome: Outcome) -> Self {
+        match outcome {
+            Outcome::Continue => Self::Continue,
+            Outcome::Respond(response) => Self::Respond(response),
+        }
+    }
+}
+
+impl Host for ComponentRunStates {}
+
+impl HostRequest for ComponentRunStates {
+    async fn method(&mut self, self_: Resource<Request>) -> Result<String, Error> {
+        Ok(SyncHostRequest::method(self, self_)?)
+    }
+
+    async fn set_method(&mut self, self_: Resource<Request>, method: String) -> Result<(), Error> {
+        Ok(SyncHostRequest::set_method(self, self_, method)?)
+    }
+
+    async fn version(&mut self, self_: Resource<Request>) -> Result<String, Error> {
+        Ok(SyncHostRequest::version(self, self_)?)
+    }
+
+    async fn headers(&mut self, self_: Resource<Request>) -> Result<Vec<(String, Vec<u8>)>, Error> {
+        Ok(SyncHostRequest::headers(self, self_)?)
+    }
+
+    async fn set_header(
+        &mut self,
+        self_: Resource<Request>,
+        key: String,
+        value: Vec<u8>,
+    ) -> Result<(), Error> {
+        Ok(SyncHostRequest::set_header(self, self_, key, value)?)
+    }
+
+    async fn get_header(
+        &mut self,
+        self_: Resource<Request>,
+        key: String,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        Ok(SyncHostRequest::get_header(self, self_, key)?)
+    }
+
+    async fn append_header(
+        &mut self,
+        self_: Resource<Request>,
+        key: String,
+        value: Vec<u8>,
+    ) -> Result<(), Error> {
+        Ok(SyncHostRequest::append_header(self, self_, key, value)?)
+    }
+
+    async fn remove_header(&mut self, self_: Resource<Request>, key: String) -> Result<(), Error> {
+        Ok(SyncHostRequest::remove_header(self, self_, key)?)
+    }
+
+    async fn uri(&mut self, self_: Resource<Request>) -> Result<String, Error> {
+        Ok(SyncHostRequest::uri(self, self_)?)
+    }
+
+    async fn set_uri(&mut self, self_: Resource<Request>, uri: String) -> Result<(), Error> {
+        Ok(SyncHostRequest::set_uri(self, self_, uri)?)
+    }
+
+    async fn scheme(&mut self, self_: Resource<Request>) -> Result<Option<String>, Error> {
+        Ok(SyncHostRequest::scheme(self, self_)?)
+    }
+
+    async fn set_scheme(&mut self, self_: Resource<Request>, scheme: String) -> Result<(), Error> {
+        Ok(SyncHostRequest::set_scheme(self, self_, scheme)?)
+    }
+
+    async fn authority(&mut self, self_: Resource<Request>) -> Result<Option<String>, Error> {
+        Ok(SyncHostRequest::authority(self, self_)?)
+    }
+
+    async fn set_authority(
+        &mut self,
+        self_: Resource<Request>,
+        authority: String,
+    ) -> Result<(), Error> {
+        Ok(SyncHostRequest::set_authority(self, self_, authority)?)
+    }
+
+    async fn host(&mut self, self_: Resource<Request>) -> Result<Option<String>, Error> {
+        Ok(SyncHostRequest::host(self, self_)?)
+    }
+
+    async fn set_host(&mut self, self_: Resource<Request>, host: String) -> Result<(), Error> {
+        Ok(SyncHostRequest::set_host(self, self_, host)?)
+    }
+
+    async fn port(&mut self, self_: Resource<Request>) -> Result<Option<u16>, Error> {
+        Ok(SyncHostRequest::port(self, self_)?)
+    }
+
+    async fn set_port(&mut self, self_: Resource<Request>, port: Option<u16>) -> Result<(), Error> {
+        Ok(SyncHostRequest::set_port(self, self_, port)?)
+    }
+
+    async fn path(&mut self, self_: Resource<Request>) -> Result<String, Error> {
+        Ok(SyncHostRequest::path(self, self_)?)
+    }
+
+    async fn set_path(&mut self, self_: Resource<Request>, path: String) -> Result<(), Error> {
+        Ok(SyncHostRequest::set_path(self, self_, path)?)
+    }
+
+    async fn query(&mut self, self_: Resource<Request>) -> Result<Option<String>, Error> {
+        Ok(SyncHostRequest::query(self, self_)?)
+    }
+
+    async fn set_query(
+        &mut self,
+        self_: Resource<Request>,
+        query: Option<String>,
+    ) -> Result<(), Error> {
+        Ok(SyncHostRequest::set_query(self, self_, query)?)
+    }
+
+    async fn body(
+        &mut self,
+        self_: Resource<Request>,
+        max_size: u64,
+    ) -> wasmtime::Result<Result<Vec<u8>, Error>> {
+        // Waits without blocking the executor, the deadline of the whole call
+        // applies.
+        let body = match self.shared_body(&self_) {
+            Ok(body) => body,
+            Err(error) => return Ok(Err(error.into())),
+        };
+        Ok(poll_fn(|cx| body.poll_read_all(cx, max_size))
+            .await
+            .map_err(Error::from))
+    }
+
+    async fn body_stream(
+        &mut self,
+        self_: Resource<Request>,
+    ) -> Result<Resource<DynInputStream>, Error> {
+        Ok(SyncHostRequest::body_stream(self, self_)?)
+    }
+
+    async fn set_body(&mut self, self_: Resource<Request>, body: Vec<u8>) -> Result<(), Error> {
+        Ok(SyncHostRequest::set_body(self, self_, body)?)
+    }
+
+    async fn drop(&mut self, rep: Resource<Request>) -> wasmtime::Result<()> {
+        SyncHostRequest::drop(self, rep)
+    }
+}
+
+impl HostResponse for ComponentRunStates {
+    async fn new(&mut self) -> wasmtime::Result<Resource<Response>> {
+        SyncHostResponse::new(self)
+    }
+
+    async fn status(&mut self, self_: Resource<Response>) -> Result<u16, Error> {
+        Ok(SyncHostResponse::status(self, self_)?)
+    }
+
+    async fn set_status(&mut self, self_: Resource<Response>, status: u16) -> Result<(), Error> {
+        Ok(SyncHostResponse::set_status(self, self_, status)?)
+    }
+
+    async fn headers(
+        &mut self,
+        self_: Resource<Response>,
+    ) -> Result<Vec<(String, Vec<u8>)>, Error> {
+        Ok(SyncHostResponse::headers(self, self_)?)
+    }
+
+    async fn set_header(
+        &mut self,
+        self_: Resource<Response>,
+        key: String,
+        value: Vec<u8>,
+    ) -> Result<(), Error> {
+        Ok(SyncHostResponse::set_header(self, self_, key, value)?)
+    }
+
+    async fn set_body(&mut self, self_: Resource<Response>, body: Vec<u8>) -> Result<(), Error> {
+        Ok(SyncHostResponse::set_body(self, self_, body)?)
+    }
+
+    async fn drop(&mut self, rep: Resource<Response>) -> wasmtime::Result<()> {
+        SyncHostResponse::drop(self, rep)
+    }
+}
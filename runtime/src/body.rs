@@ -0,0 +1,238 @@
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker, ready};
+use std::time::Instant;
+
+use bytes::{Bytes, BytesMut};
+use rama::http::dep::http_body::{self, Body as _, Frame, SizeHint};
+use rama::http::{Body, HeaderMap};
+use wasmtime::Trap;
+use wasmtime_wasi::async_trait;
+use wasmtime_wasi::p2::{InputStream, Pollable, StreamError, StreamResult};
+use wasmtime_wasi::runtime::in_tokio;
+
+use crate::Error;
+
+/// The body of a request while a component reads it. The request is forwarded
+/// with whatever the component has not consumed: all of it after `body`, which
+/// only buffers, and the unread rest after reading from `body-stream`.
+#[derive(Clone)]
+pub(crate) struct SharedBody(Arc<Mutex<ReadAhead>>);
+
+struct ReadAhead {
+    /// Data taken from `body` that no reader has consumed yet.
+    buffered: BytesMut,
+    /// Whether a stream has consumed any data.
+    consumed: bool,
+    trailers: Option<HeaderMap>,
+    /// Set once `body` has ended, to the error if it failed.
+    finished: Option<Result<(), String>>,
+    body: Body,
+}
+
+impl ReadAhead {
+    /// Takes the next frame of the body, unless it has already ended.
+    fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.finished.is_some() {
+            return Poll::Ready(());
+        }
+        match ready!(Pin::new(&mut self.body).poll_frame(cx)) {
+            Some(Ok(frame)) => match frame.into_data() {
+                Ok(data) => self.buffered.extend_from_slice(&data),
+                Err(frame) => self.trailers = frame.into_trailers().ok(),
+            },
+            Some(Err(err)) => self.finished = Some(Err(err.to_string())),
+            None => self.finished = Some(Ok(())),
+        }
+        Poll::Ready(())
+    }
+}
+
+impl SharedBody {
+    pub(crate) fn new(body: Body) -> Self {
+        Self(Arc::new(Mutex::new(ReadAhead {
+            buffered: BytesMut::new(),
+            consumed: false,
+            trailers: None,
+            finished: None,
+            body,
+        })))
+    }
+
+    /// A body for the request that yields what no reader has consumed.
+    pub(crate) fn to_body(&self) -> Body {
+        Body::new(self.clone())
+    }
+
+    /// Whether a stream has consumed part of the body, which is then shorter
+    /// than announced.
+    pub(crate) fn is_consumed(&self) -> bool {
+        self.0.lock().unwrap().consumed
+    }
+
+    /// Buffers the whole body and returns a copy of it. Fails without reading
+    /// on if the body is, or turns out to be, longer than `max_size` bytes.
+    pub(crate) fn poll_read_all(
+        &self,
+        cx: &mut Context<'_>,
+        max_size: u64,
+    ) -> Poll<Result<Vec<u8>, Error>> {
+        let mut state = self.0.lock().unwrap();
+        loop {
+            let mut size = state.buffered.len() as u64;
+            if state.finished.is_none() {
+                size += state.body.size_hint().lower();
+            }
+            if size > max_size {
+                return Poll::Ready(Err(Error::InvalidBody(format!(
+                    "Body of at least {} bytes exceeds maximum size of {} bytes",
+                    size, max_size
+                ))));
+            }
+            match &state.finished {
+                Some(Ok(())) => return Poll::Ready(Ok(state.buffered.to_vec())),
+                Some(Err(message)) => {
+                    return Poll::Ready(Err(Error::InvalidBody(format!(
+                        "Error reading body: {}",
+                        message
+                    ))));
+                }
+                None => ready!(state.poll_next(cx)),
+            }
+        }
+    }
+
+    /// Blocks the calling thread until [`SharedBody::poll_read_all`] is done,
+    /// the way the blocking `wasi:io` calls wait. Traps with
+    /// [`Trap::Interrupt`] if `deadline` passes first.
+    pub(crate) fn read_all_blocking(
+        &self,
+        max_size: u64,
+        deadline: Option<Instant>,
+    ) -> wasmtime::Result<Result<Vec<u8>, Error>> {
+        in_tokio(async {
+            let read = std::future::poll_fn(|cx| self.poll_read_all(cx, max_size));
+            match deadline {
+                Some(deadline) => tokio::time::timeout_at(deadline.into(), read)
+                    .await
+                    .map_err(|_| Trap::Interrupt.into()),
+                None => Ok(read.await),
+            }
+        })
+    }
+}
+
+impl http_body::Body for SharedBody {
+    type Data = Bytes;
+    type Error = String;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, String>>> {
+        let mut state = self.0.lock().unwrap();
+        while state.buffered.is_empty() && state.finished.is_none() {
+            ready!(state.poll_next(cx));
+        }
+        if !state.buffered.is_empty() {
+            return Poll::Ready(Some(Ok(Frame::data(state.buffered.split().freeze()))));
+        }
+        if let Some(trailers) = state.trailers.take() {
+            return Poll::Ready(Some(Ok(Frame::trailers(trailers))));
+        }
+        // An error is reported once, after which the body ends.
+        match state.finished.replace(Ok(())) {
+            Some(Err(message)) => Poll::Ready(Some(Err(message))),
+            _ => Poll::Ready(None),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        let state = self.0.lock().unwrap();
+        state.buffered.is_empty()
+            && state.trailers.is_none()
+            && match &state.finished {
+                Some(finished) => finished.is_ok(),
+                None => state.body.is_end_stream(),
+            }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        let state = self.0.lock().unwrap();
+        let buffered = state.buffered.len() as u64;
+        if state.finished.is_some() {
+            return SizeHint::with_exact(buffered);
+        }
+        let body = state.body.size_hint();
+        let mut hint = SizeHint::new();
+        hint.set_lower(body.lower() + buffered);
+        if let Some(upper) = body.upper() {
+            hint.set_upper(upper + buffered);
+        }
+        hint
+    }
+}
+
+/// A `wasi:io` input stream over a request body, which reads the body as it
+/// arrives instead of buffering it. Reads trap once the deadline of the call
+/// has passed.
+pub(crate) struct BodyStream {
+    body: SharedBody,
+    deadline: Option<Instant>,
+}
+
+impl BodyStream {
+    pub(crate) fn new(body: SharedBody, deadline: Option<Instant>) -> Self {
+        Self { body, deadline }
+    }
+}
+
+impl InputStream for BodyStream {
+    fn read(&mut self, size: usize) -> StreamResult<Bytes> {
+        let mut state = self.body.0.lock().unwrap();
+        if state.buffered.is_empty() {
+            // Takes a frame only if the body has one ready.
+            let _ = state.poll_next(&mut Context::from_waker(Waker::noop()));
+        }
+        if !state.buffered.is_empty() {
+            let size = size.min(state.buffered.len());
+            state.consumed = true;
+            return Ok(state.buffered.split_to(size).freeze());
+        }
+        match &state.finished {
+            None if self
+                .deadline
+                .is_some_and(|deadline| deadline <= Instant::now()) =>
+            {
+                Err(StreamError::Trap(Trap::Interrupt.into()))
+            }
+            None => Ok(Bytes::new()),
+            Some(Ok(())) => Err(StreamError::Closed),
+            Some(Err(message)) => Err(StreamError::LastOperationFailed(anyhow::anyhow!(
+                "Error reading body: {}",
+                message
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl Pollable for BodyStream {
+    async fn ready(&mut self) {
+        let ready = std::future::poll_fn(|cx| {
+            let mut state = self.body.0.lock().unwrap();
+            if state.buffered.is_empty() {
+                state.poll_next(cx)
+            } else {
+                Poll::Ready(())
+            }
+        });
+        match self.deadline {
+            // The read that follows traps if the deadline has passed.
+            Some(deadline) => {
+                let _ = tokio::time::timeout_at(deadline.into(), ready).await;
+            }
+            None => ready.await,
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use wasmtime::Engine;
+use wasmtime::component::Component;
+
+/// An on-disk cache of compiled components.
+///
+/// Entries are named after the SHA-256 of the wasm and of the engine's
+/// compilation settings, which include the wasmtime version, so changing
+/// either misses the cache. Next to each entry lies the SHA-256 of the entry
+/// itself, which is checked before the entry is loaded.
+#[derive(Clone)]
+pub(crate) struct ComponentCache {
+    dir: PathBuf,
+}
+
+impl ComponentCache {
+    pub(crate) fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Loads the compiled `wasm` from the cache, or compiles it and stores the
+    /// result if there is no intact entry compatible with `engine`. `wasm_hash`
+    /// is the hex encoded SHA-256 of `wasm`. Failing to store the result is
+    /// logged, as the compiled component can be used all the same.
+    pub(crate) fn load(&self, engine: &Engine, wasm: &[u8], wasm_hash: &str) -> Result<Component> {
+        let mut hasher = Sha256Hasher(Sha256::new());
+        engine.precompile_compatibility_hash().hash(&mut hasher);
+        let entry = self
+            .dir
+            .join(format!("{}-{:x}.cwasm", wasm_hash, hasher.0.finalize()));
+        let checksum = entry.with_extension("sha256");
+
+        if let Some(artifact) = Self::read_intact(&entry, &checksum) {
+            // SAFETY: `RuntimeConfig::cache_dir` requires that only trusted
+            // users can write to the cache, so the entry was written by `store`
+            // from the output of `Component::serialize`. The checksum only
+            // catches entries that were cut short or damaged, and is checked
+            // on the very bytes deserialized here. Wasmtime additionally
+            // rejects artifacts from other versions or incompatible engines.
+            if let Ok(component) = unsafe { Component::deserialize(engine, &artifact) } {
+                return Ok(component);
+            }
+        }
+
+        let component = Component::new(engine, wasm)?;
+        let stored = component
+            .serialize()
+            .and_then(|artifact| self.store(&entry, &checksum, &artifact));
+        if let Err(error) = stored {
+            tracing::warn!(
+                cache = %self.dir.display(),
+                error = format!("{:#}", error),
+                "Cannot write compiled component to cache"
+            );
+        }
+        Ok(component)
+    }
+
+    /// Reads the entry, if it matches its checksum.
+    fn read_intact(entry: &Path, checksum: &Path) -> Option<Vec<u8>> {
+        let artifact = fs::read(entry).ok()?;
+        let checksum = fs::read_to_string(checksum).ok()?;
+        (format!("{:x}", Sha256::digest(&artifact)) == checksum).then_some(artifact)
+    }
+
+    /// Writes `artifact` and its checksum. Entries compiled by other engines
+    /// are left in place, as another runtime may still be using them.
+    fn store(&self, entry: &Path, checksum: &Path, artifact: &[u8]) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        // Both files are written under a temporary name and then renamed, so
+        // a concurrent reader never sees them half written.
+        write_atomic(entry, artifact)?;
+        write_atomic(
+            checksum,
+            format!("{:x}", Sha256::digest(artifact)).as_bytes(),
+        )?;
+        Ok(())
+    }
+}
+
+/// Feeds what is hashed into SHA-256, so entry names are stable across builds,
+/// unlike those of the standard library's hashers.
+struct Sha256Hasher(Sha256);
+
+impl Hasher for Sha256Hasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        u64::from_le_bytes(digest[..8].try_into().unwrap())
+    }
+}
+
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let temporary = path.with_extension(format!("tmp{}", std::process::id()));
+    fs::write(&temporary, contents)?;
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
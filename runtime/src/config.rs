@@ -0,0 +1,130 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use wasmtime::PoolingAllocationConfig;
+
+use crate::{InstanceLimits, RequestQuotas};
+
+/// Engine wide settings for a [`Runtime`](crate::Runtime).
+#[derive(Debug, Clone)]
+pub struct RuntimeConfig {
+    /// Meter the instructions executed by components with fuel, so calls can be
+    /// bounded by a budget set through
+    /// [`Runtime::set_fuel_budget`](crate::Runtime::set_fuel_budget).
+    pub consume_fuel: bool,
+    /// Interrupt components on wall-clock deadlines, set through
+    /// [`Runtime::set_deadline`](crate::Runtime::set_deadline). A background
+    /// thread advances the epoch every `epoch_tick`, which is also the
+    /// granularity of deadlines. Calls may run up to one tick past their
+    /// deadline. The tick must not be zero.
+    pub epoch_tick: Option<Duration>,
+    /// Limits applied to every newly added instance, adjustable per instance
+    /// through [`Runtime::set_limits`](crate::Runtime::set_limits).
+    pub instance_limits: InstanceLimits,
+    /// Run components on Tokio through
+    /// [`Runtime::add_instance_async`](crate::Runtime::add_instance_async) and
+    /// [`Runtime::call_handle_async`](crate::Runtime::call_handle_async) instead
+    /// of blocking the calling thread.
+    pub async_support: bool,
+    /// Quotas on how far a component may grow each request it handles.
+    pub request_quotas: RequestQuotas,
+    /// Number of routers instantiated for every instance, each in its own
+    /// store, which bounds how many calls an instance handles concurrently.
+    pub pool_size: usize,
+    /// How long a call waits for a router of its instance to become free
+    /// before it fails with
+    /// [`HandleError::PoolExhausted`](crate::HandleError::PoolExhausted).
+    /// Calls wait indefinitely if this is `None`.
+    pub pool_timeout: Option<Duration>,
+    /// Give every call a brand new store and instance, so no state in
+    /// component globals carries over from one request to the next. Instances
+    /// keep no pool then, and `pool_size` and `pool_timeout` do not apply.
+    pub isolate_calls: bool,
+    /// Allocate instances from wasmtime's pooling allocator instead of mapping
+    /// fresh memory for every instantiation. This makes instantiation cheap
+    /// enough for [`isolate_calls`](Self::isolate_calls) at high request rates.
+    pub pooling: Option<PoolingConfig>,
+    /// Directory in which compiled components are cached, so a component is
+    /// only compiled again if it or the engine settings change.
+    ///
+    /// Cached entries are native code that is loaded without being verified,
+    /// like the artifacts of [`Runtime::add_precompiled`](crate::Runtime::add_precompiled).
+    /// The directory must only be writable by trusted users, as anyone who
+    /// can write to it can run arbitrary code in the runtime.
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            consume_fuel: false,
+            epoch_tick: None,
+            instance_limits: InstanceLimits::default(),
+            async_support: false,
+            request_quotas: RequestQuotas::default(),
+            pool_size: 1,
+            pool_timeout: None,
+            isolate_calls: false,
+            pooling: None,
+            cache_dir: None,
+        }
+    }
+}
+
+/// Settings of the pooling allocator, which reserves slots for all instances
+/// up front and reuses them instead of mapping and unmapping memory.
+#[derive(Debug, Clone)]
+pub struct PoolingConfig {
+    /// Maximum number of router instances alive at once, across all stores.
+    pub max_instances: u32,
+    /// Maximum number of core instances, and as many tables, that a single
+    /// router instance may create.
+    pub max_core_instances_per_instance: u32,
+    /// Maximum number of linear memories a single router instance may create.
+    pub max_memories_per_instance: u32,
+    /// Maximum size in bytes of each linear memory.
+    pub max_memory_size: usize,
+    /// Number of freed slots kept warm, so instantiating the same component
+    /// again can reuse them without setting them up from scratch.
+    pub max_unused_warm_slots: u32,
+    /// Bytes at the start of each reused memory slot that are zeroed in place
+    /// rather than handed back to the kernel.
+    pub memory_keep_resident: usize,
+}
+
+impl Default for PoolingConfig {
+    fn default() -> Self {
+        Self {
+            max_instances: 1000,
+            max_core_instances_per_instance: 32,
+            max_memories_per_instance: 2,
+            max_memory_size: 64 << 20,
+            max_unused_warm_slots: 100,
+            memory_keep_resident: 1 << 20,
+        }
+    }
+}
+
+impl PoolingConfig {
+    pub(crate) fn allocation_config(&self) -> PoolingAllocationConfig {
+        let core_instances = self
+            .max_instances
+            .saturating_mul(self.max_core_instances_per_instance);
+        let memories = self
+            .max_instances
+            .saturating_mul(self.max_memories_per_instance);
+        let mut config = PoolingAllocationConfig::new();
+        config
+            .total_component_instances(self.max_instances)
+            .total_core_instances(core_instances)
+            .total_tables(core_instances)
+            .total_memories(memories)
+            .max_core_instances_per_component(self.max_core_instances_per_instance)
+            .max_tables_per_component(self.max_core_instances_per_instance)
+            .max_memories_per_component(self.max_memories_per_instance)
+            .max_memory_size(self.max_memory_size)
+            .max_unused_warm_slots(self.max_unused_warm_slots)
+            .linear_memory_keep_resident(self.memory_keep_resident);
+        config
+    }
+}
@@ -0,0 +1,174 @@
+use std::fmt;
+
+use rama::http::StatusCode;
+use semver::Version;
+use wasmtime::Trap;
+use wasmtime::component::ResourceTableError;
+
+use crate::Error;
+use crate::limits::{LimitExceeded, QuotaExceeded};
+
+/// Errors returned by [`Runtime::call_handle`](crate::Runtime::call_handle).
+#[derive(Debug)]
+pub enum HandleError {
+    /// No instance is registered under the given id.
+    UnknownInstance(usize),
+    /// The component tried to assign an unparsable URI.
+    InvalidUri(String),
+    /// The component tried to assign an illegal method token.
+    InvalidMethod(String),
+    /// The component used an illegal header name.
+    InvalidHeaderName(String),
+    /// The component used an illegal header value.
+    InvalidHeaderValue(String),
+    /// The component used a status code outside of `100..=999`.
+    InvalidStatus(String),
+    /// The body could not be read or exceeded the requested size.
+    InvalidBody(String),
+    /// The component referred to a resource that does not exist.
+    NotFound(String),
+    /// The component rejected the request with the given status code.
+    Rejected(StatusCode),
+    /// The component exceeded one of the [`RequestQuotas`](crate::RequestQuotas).
+    QuotaExceeded(String),
+    /// The component reported an internal failure.
+    Internal(String),
+    /// The call used up its fuel budget.
+    OutOfFuel,
+    /// The call exceeded its deadline.
+    Timeout,
+    /// The component grew beyond its [`InstanceLimits`](crate::InstanceLimits).
+    LimitExceeded(String),
+    /// Every router of the instance stayed busy for the whole
+    /// [`RuntimeConfig::pool_timeout`](crate::RuntimeConfig::pool_timeout).
+    PoolExhausted,
+    /// `call_handle` was used on a runtime with
+    /// [`RuntimeConfig::async_support`](crate::RuntimeConfig::async_support),
+    /// or `call_handle_async` on one without.
+    WrongCallMode(&'static str),
+    /// Wasmtime failed to run the component, e.g. because it trapped.
+    Runtime(anyhow::Error),
+}
+
+impl fmt::Display for HandleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownInstance(id) => write!(f, "Couldn't find instance with id {}", id),
+            Self::InvalidUri(message) => write!(f, "Invalid uri: {}", message),
+            Self::InvalidMethod(message) => write!(f, "Invalid method: {}", message),
+            Self::InvalidHeaderName(message) => write!(f, "Invalid header name: {}", message),
+            Self::InvalidHeaderValue(message) => write!(f, "Invalid header value: {}", message),
+            Self::InvalidStatus(message) => write!(f, "Invalid status: {}", message),
+            Self::InvalidBody(message) => write!(f, "Invalid body: {}", message),
+            Self::NotFound(message) => write!(f, "Not found: {}", message),
+            Self::Rejected(status) => write!(f, "Rejected with status {}", status),
+            Self::QuotaExceeded(message) => write!(f, "Quota exceeded: {}", message),
+            Self::Internal(message) => write!(f, "Component error: {}", message),
+            Self::OutOfFuel => write!(f, "Component ran out of fuel"),
+            Self::Timeout => write!(f, "Component exceeded its deadline"),
+            Self::PoolExhausted => write!(f, "All routers of the instance are busy"),
+            Self::LimitExceeded(message) => write!(f, "Limit exceeded: {}", message),
+            Self::WrongCallMode(message) => write!(f, "Wrong call mode: {}", message),
+            Self::Runtime(error) => write!(f, "Runtime error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for HandleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Runtime(error) => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Why a component cannot be loaded, as returned by the `add_instance*`
+/// methods of [`Runtime`](crate::Runtime). Downcast their error to match on it.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The component imports something the runtime does not provide, or does
+    /// not export the router interface of this runtime, e.g. another version
+    /// of it.
+    NotARouter {
+        name: String,
+        version: Version,
+        /// Names of the component's imports.
+        imports: Vec<String>,
+        /// Names of the component's exports.
+        exports: Vec<String>,
+        /// Why wasmtime refused the component.
+        source: anyhow::Error,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotARouter {
+                name,
+                version,
+                imports,
+                exports,
+                ..
+            } => write!(
+                f,
+                "Cannot use component {}@{} as a router, it imports [{}] and exports [{}]",
+                name,
+                version,
+                imports.join(", "),
+                exports.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::NotARouter { source, .. } => Some(source.as_ref()),
+        }
+    }
+}
+
+impl From<Error> for HandleError {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::InvalidUri(message) => Self::InvalidUri(message),
+            Error::InvalidMethod(message) => Self::InvalidMethod(message),
+            Error::InvalidHeaderName(message) => Self::InvalidHeaderName(message),
+            Error::InvalidHeaderValue(message) => Self::InvalidHeaderValue(message),
+            Error::InvalidStatus(message) => Self::InvalidStatus(message),
+            Error::InvalidBody(message) => Self::InvalidBody(message),
+            Error::NotFound(message) => Self::NotFound(message),
+            Error::Rejected(status) => match StatusCode::from_u16(status) {
+                Ok(status) => Self::Rejected(status),
+                Err(_) => Self::InvalidStatus(format!("Rejected with status {}", status)),
+            },
+            Error::QuotaExceeded(message) => Self::QuotaExceeded(message),
+            Error::Internal(message) => Self::Internal(message),
+        }
+    }
+}
+
+impl From<anyhow::Error> for HandleError {
+    fn from(error: anyhow::Error) -> Self {
+        if let Some(limit_exceeded) = error.downcast_ref::<LimitExceeded>() {
+            return Self::LimitExceeded(limit_exceeded.to_string());
+        }
+        if let Some(quota_exceeded) = error.downcast_ref::<QuotaExceeded>() {
+            return Self::QuotaExceeded(quota_exceeded.to_string());
+        }
+        match error.downcast_ref::<Trap>() {
+            Some(Trap::OutOfFuel) => Self::OutOfFuel,
+            Some(Trap::Interrupt) => Self::Timeout,
+            _ => Self::Runtime(error),
+        }
+    }
+}
+
+impl From<ResourceTableError> for HandleError {
+    fn from(error: ResourceTableError) -> Self {
+        Self::Runtime(error.into())
+    }
+}
@@ -1,31 +1,104 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
-use anyhow::{Result, anyhow};
-use rama::http::{HeaderName, HeaderValue, Request as RamaRequest, Uri};
-use wasmtime::component::{Component, Linker, Resource, ResourceTable, TypedFunc, bindgen};
-use wasmtime::{Engine, Store};
-use wasmtime_wasi::p2::{IoView, WasiCtx, WasiCtxBuilder, WasiView};
+use anyhow::{Context, Result};
+use rama::http::dep::http::uri::{Authority, Parts as UriParts, PathAndQuery, Scheme};
+use rama::http::{
+    Body, HeaderName, HeaderValue, Method, Request as RamaRequest, Response as RamaResponse,
+    StatusCode, Uri, Version as HttpVersion, header,
+};
+use sha2::{Digest, Sha256};
+use wasmtime::component::{Component, Linker, Resource, ResourceTable, bindgen};
+use wasmtime::{Engine, Precompiled, Store, Trap, UpdateDeadline};
+use wasmtime_wasi::p2::{DynInputStream, IoView, WasiCtx, WasiView};
 
-pub type Request = ();
-pub type Router = TypedFunc<(Resource<Request>,), (Result<(), String>,)>;
+pub type Request = RamaRequest;
+pub type Response = RamaResponse;
 
 bindgen!({
     path: "../wit/",
     world: "crossroads",
+    require_store_data_send: true,
+    trappable_imports: ["[constructor]response", "[method]request.body"],
     with: {
+        "wasi:io": wasmtime_wasi::p2::bindings::io,
         "wit:crossroads/types/request": Request,
+        "wit:crossroads/types/response": Response,
     }
 });
 
-pub(self) use wit::crossroads::types::{Host, HostRequest};
+use wit::crossroads::types::{Error, Host, HostRequest, HostResponse, Outcome};
+
+mod asynchronous;
+mod body;
+mod cache;
+mod config;
+mod error;
+mod limits;
+mod pool;
+mod registry;
+mod wasi_config;
+mod watch;
+
+pub use config::{PoolingConfig, RuntimeConfig};
+pub use error::{HandleError, LoadError};
+pub use limits::{InstanceLimits, RequestQuotas};
+pub use registry::ComponentInfo;
+pub use semver::Version;
+pub use wasi_config::{Preopen, Stdio, WasiConfig};
+
+use body::{BodyStream, SharedBody};
+use cache::ComponentCache;
+use limits::{Limiter, QuotaExceeded};
+use pool::{Checkout, Pool, PooledRouter};
+use registry::Registry;
+use watch::Source;
+
+/// What a router decided to do with a request.
+pub enum Routed {
+    /// The request, possibly modified, should be forwarded upstream.
+    Forward(RamaRequest),
+    /// The router answered the request itself.
+    Respond(RamaResponse),
+}
+
+/// The result of a successful [`Runtime::call_handle`].
+pub struct Handled {
+    /// What the router decided to do with the request.
+    pub routed: Routed,
+    /// Fuel the call consumed, if fuel metering is enabled. Fuel burnt by
+    /// calls that fail is only counted in [`InstanceStats::fuel_consumed`].
+    pub fuel_consumed: Option<u64>,
+}
+
+/// Counters kept for every router instance.
+#[derive(Debug, Clone, Default)]
+pub struct InstanceStats {
+    /// Number of calls to the router.
+    pub calls: u64,
+    /// Number of calls that trapped, after which the instance was replaced.
+    pub traps: u64,
+    /// Number of times the instance hit its [`InstanceLimits`].
+    pub limit_violations: u64,
+    /// Fuel consumed by all calls, including those that failed or trapped.
+    /// Stays zero unless fuel metering is enabled.
+    pub fuel_consumed: u64,
+}
 
 pub struct ComponentRunStates {
     pub wasi_ctx: WasiCtx,
     pub table: ResourceTable,
-    pub requests: HashMap<u32, RamaRequest>,
+    limiter: Limiter,
+    quotas: RequestQuotas,
+    /// The body of the request being handled, once the component reads it.
+    body: Option<SharedBody>,
+    /// Responses and body streams the component created in the current call.
+    resources_created: usize,
+    /// When the current call has to be done by, if it has a deadline.
+    deadline: Option<Instant>,
 }
 
 impl IoView for ComponentRunStates {
@@ -42,16 +115,163 @@ impl WasiView for ComponentRunStates {
 
 impl Host for ComponentRunStates {}
 
+impl ComponentRunStates {
+    /// Returns the body of `request` as shared with the component's readers,
+    /// putting it in place when the component first reads it.
+    fn shared_body(&mut self, request: &Resource<Request>) -> Result<SharedBody, Error> {
+        let request = self
+            .table
+            .get_mut(request)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        if let Some(body) = &self.body {
+            return Ok(body.clone());
+        }
+        let body = SharedBody::new(std::mem::take(request.body_mut()));
+        *request.body_mut() = body.to_body();
+        self.body = Some(body.clone());
+        Ok(body)
+    }
+
+    /// Counts a resource created by the component, unless that exceeds
+    /// [`RequestQuotas::max_resources`].
+    fn count_resource(&mut self) -> Result<(), QuotaExceeded> {
+        self.quotas.check_resources(self.resources_created)?;
+        self.resources_created += 1;
+        Ok(())
+    }
+}
+
+/// Rebuilds the uri of `request` from its parts after `modify` has changed them.
+/// An origin-form uri like `/path` that gets an authority also gets the `http`
+/// scheme, and one that gets a scheme takes its authority from the Host header.
+/// A Host header is kept in line with the authority of the uri.
+fn modify_uri(
+    request: &mut RamaRequest,
+    quotas: &RequestQuotas,
+    modify: impl FnOnce(&mut UriParts) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let mut parts = request.uri().clone().into_parts();
+    modify(&mut parts)?;
+    match (&parts.scheme, &parts.authority) {
+        (None, Some(_)) => parts.scheme = Some(Scheme::HTTP),
+        (Some(_), None) => {
+            let host = request
+                .headers()
+                .get(header::HOST)
+                .and_then(|host| host.to_str().ok())
+                .ok_or_else(|| {
+                    Error::InvalidUri("Uri has no authority and request no Host header".to_string())
+                })?;
+            let authority = Authority::from_str(host)
+                .map_err(|err| Error::InvalidUri(format!("Error using Host header: {}", err)))?;
+            parts.authority = Some(authority);
+        }
+        _ => {}
+    }
+    if parts.scheme.is_some() && parts.path_and_query.is_none() {
+        parts.path_and_query = Some(PathAndQuery::from_static("/"));
+    }
+    let uri = Uri::from_parts(parts)
+        .map_err(|err| Error::InvalidUri(format!("Error assigning uri: {}", err)))?;
+    quotas.check_uri(&uri)?;
+    if let Some(authority) = uri.authority()
+        && request.headers().contains_key(header::HOST)
+        && request.uri().authority() != Some(authority)
+    {
+        let host = match authority.port() {
+            Some(port) => format!("{}:{}", authority.host(), port),
+            None => authority.host().to_string(),
+        };
+        let host = HeaderValue::from_str(&host)
+            .map_err(|err| Error::InvalidHeaderValue(err.to_string()))?;
+        quotas.check_headers(
+            request.headers(),
+            Some(&header::HOST),
+            (&header::HOST, &host),
+        )?;
+        request.headers_mut().insert(header::HOST, host);
+    }
+    *request.uri_mut() = uri;
+    Ok(())
+}
+
+/// Builds an authority from its components, keeping the user info if present.
+fn build_authority(
+    userinfo: Option<&str>,
+    host: &str,
+    port: Option<u16>,
+) -> Result<Authority, Error> {
+    let mut authority = String::new();
+    if let Some(userinfo) = userinfo {
+        authority.push_str(userinfo);
+        authority.push('@');
+    }
+    authority.push_str(host);
+    if let Some(port) = port {
+        authority.push_str(&format!(":{}", port));
+    }
+    Authority::from_str(&authority)
+        .map_err(|err| Error::InvalidUri(format!("Error assigning authority: {}", err)))
+}
+
+/// Splits the user info off an authority.
+fn userinfo(authority: &Authority) -> Option<&str> {
+    authority
+        .as_str()
+        .rsplit_once('@')
+        .map(|(userinfo, _)| userinfo)
+}
+
 impl HostRequest for ComponentRunStates {
-    fn headers(&mut self, self_: Resource<Request>) -> Result<Vec<(String, String)>, String> {
+    fn method(&mut self, self_: Resource<Request>) -> Result<String, Error> {
+        let request = self
+            .table
+            .get(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        Ok(request.method().to_string())
+    }
+
+    fn set_method(&mut self, self_: Resource<Request>, method: String) -> Result<(), Error> {
+        let method = Method::from_bytes(method.as_bytes())
+            .map_err(|err| Error::InvalidMethod(format!("Error assigning method: {}", err)))?;
         let request = self
-            .requests
-            .get(&self_.rep())
-            .ok_or_else(|| "Request not in resource table".to_string())?;
+            .table
+            .get_mut(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        *request.method_mut() = method;
+        Ok(())
+    }
+
+    fn version(&mut self, self_: Resource<Request>) -> Result<String, Error> {
+        let request = self
+            .table
+            .get(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        let version = match request.version() {
+            HttpVersion::HTTP_09 => "HTTP/0.9",
+            HttpVersion::HTTP_10 => "HTTP/1.0",
+            HttpVersion::HTTP_11 => "HTTP/1.1",
+            HttpVersion::HTTP_2 => "HTTP/2.0",
+            HttpVersion::HTTP_3 => "HTTP/3.0",
+            version => {
+                return Err(Error::Internal(format!(
+                    "Unsupported HTTP version {:?}",
+                    version
+                )));
+            }
+        };
+        Ok(version.to_string())
+    }
+
+    fn headers(&mut self, self_: Resource<Request>) -> Result<Vec<(String, Vec<u8>)>, Error> {
+        let request = self
+            .table
+            .get(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
         let header = request
             .headers()
             .iter()
-            .map(|(key, value)| (key.to_string(), value.to_str().unwrap().to_string()))
+            .map(|(key, value)| (key.to_string(), value.as_bytes().to_vec()))
             .collect();
         Ok(header)
     }
@@ -60,113 +280,1306 @@ impl HostRequest for ComponentRunStates {
         &mut self,
         self_: Resource<Request>,
         key: String,
-        value: String,
-    ) -> Result<(), String> {
-        let header_key = HeaderName::from_str(&key).map_err(|err| err.to_string())?;
-        let header_value = HeaderValue::from_str(&value).map_err(|err| err.to_string())?;
-        self.requests
-            .get_mut(&self_.rep())
-            .ok_or_else(|| "Request not in resource table".to_string())?
+        value: Vec<u8>,
+    ) -> Result<(), Error> {
+        let header_key =
+            HeaderName::from_str(&key).map_err(|err| Error::InvalidHeaderName(err.to_string()))?;
+        let header_value = HeaderValue::from_bytes(&value)
+            .map_err(|err| Error::InvalidHeaderValue(err.to_string()))?;
+        let headers = self
+            .table
+            .get_mut(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?
+            .headers_mut();
+        self.quotas
+            .check_headers(headers, Some(&header_key), (&header_key, &header_value))?;
+        headers.insert(header_key, header_value);
+        Ok(())
+    }
+
+    fn get_header(&mut self, self_: Resource<Request>, key: String) -> Result<Vec<Vec<u8>>, Error> {
+        let header_key =
+            HeaderName::from_str(&key).map_err(|err| Error::InvalidHeaderName(err.to_string()))?;
+        let request = self
+            .table
+            .get(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        let values = request
+            .headers()
+            .get_all(header_key)
+            .iter()
+            .map(|value| value.as_bytes().to_vec())
+            .collect();
+        Ok(values)
+    }
+
+    fn append_header(
+        &mut self,
+        self_: Resource<Request>,
+        key: String,
+        value: Vec<u8>,
+    ) -> Result<(), Error> {
+        let header_key =
+            HeaderName::from_str(&key).map_err(|err| Error::InvalidHeaderName(err.to_string()))?;
+        let header_value = HeaderValue::from_bytes(&value)
+            .map_err(|err| Error::InvalidHeaderValue(err.to_string()))?;
+        let headers = self
+            .table
+            .get_mut(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?
+            .headers_mut();
+        self.quotas
+            .check_headers(headers, None, (&header_key, &header_value))?;
+        headers.append(header_key, header_value);
+        Ok(())
+    }
+
+    fn remove_header(&mut self, self_: Resource<Request>, key: String) -> Result<(), Error> {
+        let header_key =
+            HeaderName::from_str(&key).map_err(|err| Error::InvalidHeaderName(err.to_string()))?;
+        self.table
+            .get_mut(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?
             .headers_mut()
-            .insert(header_key, header_value);
+            .remove(header_key);
         Ok(())
     }
 
-    fn uri(&mut self, self_: Resource<Request>) -> Result<String, String> {
+    fn uri(&mut self, self_: Resource<Request>) -> Result<String, Error> {
         let request = self
-            .requests
-            .get(&self_.rep())
-            .ok_or_else(|| "Request not in resource table".to_string())?;
+            .table
+            .get(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
         Ok(request.uri().to_string())
     }
 
-    fn set_uri(&mut self, self_: Resource<Request>, uri: String) -> Result<(), String> {
+    fn set_uri(&mut self, self_: Resource<Request>, uri: String) -> Result<(), Error> {
         let uri = Uri::from_str(&uri)
-            .map_err(|err| format!("Error assigning uri: {}", err.to_string()))?;
+            .map_err(|err| Error::InvalidUri(format!("Error assigning uri: {}", err)))?;
+        self.quotas.check_uri(&uri)?;
         let request = self
-            .requests
-            .get_mut(&self_.rep())
-            .ok_or_else(|| "Request not in resource table".to_string())?;
+            .table
+            .get_mut(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
         *request.uri_mut() = uri;
         Ok(())
     }
 
+    fn scheme(&mut self, self_: Resource<Request>) -> Result<Option<String>, Error> {
+        let request = self
+            .table
+            .get(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        Ok(request.uri().scheme_str().map(str::to_string))
+    }
+
+    fn set_scheme(&mut self, self_: Resource<Request>, scheme: String) -> Result<(), Error> {
+        let scheme = Scheme::from_str(&scheme)
+            .map_err(|err| Error::InvalidUri(format!("Error assigning scheme: {}", err)))?;
+        let request = self
+            .table
+            .get_mut(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        modify_uri(request, &self.quotas, |parts| {
+            parts.scheme = Some(scheme);
+            Ok(())
+        })
+    }
+
+    fn authority(&mut self, self_: Resource<Request>) -> Result<Option<String>, Error> {
+        let request = self
+            .table
+            .get(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        Ok(request.uri().authority().map(Authority::to_string))
+    }
+
+    fn set_authority(&mut self, self_: Resource<Request>, authority: String) -> Result<(), Error> {
+        let authority = Authority::from_str(&authority)
+            .map_err(|err| Error::InvalidUri(format!("Error assigning authority: {}", err)))?;
+        let request = self
+            .table
+            .get_mut(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        modify_uri(request, &self.quotas, |parts| {
+            parts.authority = Some(authority);
+            Ok(())
+        })
+    }
+
+    fn host(&mut self, self_: Resource<Request>) -> Result<Option<String>, Error> {
+        let request = self
+            .table
+            .get(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        Ok(request.uri().host().map(str::to_string))
+    }
+
+    fn set_host(&mut self, self_: Resource<Request>, host: String) -> Result<(), Error> {
+        // Colons are only part of a host inside a bracketed IPv6 literal, so
+        // user info and ports cannot be slipped in with the host.
+        let ipv6 = host.starts_with('[') && host.ends_with(']');
+        if host.contains(['@', '/']) || (!ipv6 && host.contains(':')) {
+            return Err(Error::InvalidUri(
+                "Host must not contain `@`, `/` or a port, set the port separately".to_string(),
+            ));
+        }
+        let request = self
+            .table
+            .get_mut(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        modify_uri(request, &self.quotas, |parts| {
+            let authority = match &parts.authority {
+                Some(authority) => {
+                    build_authority(userinfo(authority), &host, authority.port_u16())?
+                }
+                None => build_authority(None, &host, None)?,
+            };
+            parts.authority = Some(authority);
+            Ok(())
+        })
+    }
+
+    fn port(&mut self, self_: Resource<Request>) -> Result<Option<u16>, Error> {
+        let request = self
+            .table
+            .get(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        Ok(request.uri().port_u16())
+    }
+
+    fn set_port(&mut self, self_: Resource<Request>, port: Option<u16>) -> Result<(), Error> {
+        let request = self
+            .table
+            .get_mut(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        modify_uri(request, &self.quotas, |parts| {
+            let Some(authority) = &parts.authority else {
+                return Err(Error::InvalidUri(
+                    "Uri has no host to set a port on".to_string(),
+                ));
+            };
+            parts.authority = Some(build_authority(
+                userinfo(authority),
+                authority.host(),
+                port,
+            )?);
+            Ok(())
+        })
+    }
+
+    fn path(&mut self, self_: Resource<Request>) -> Result<String, Error> {
+        let request = self
+            .table
+            .get(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        Ok(request.uri().path().to_string())
+    }
+
+    fn set_path(&mut self, self_: Resource<Request>, path: String) -> Result<(), Error> {
+        if path.contains(['?', '#']) {
+            return Err(Error::InvalidUri(
+                "Path must not contain `?` or `#`, set the query separately".to_string(),
+            ));
+        }
+        // The path is not validated when the uri is put together, and one that
+        // does not start with `/` would run into the authority.
+        if !path.starts_with('/') && path != "*" {
+            return Err(Error::InvalidUri(
+                "Path must start with `/` or be `*`".to_string(),
+            ));
+        }
+        let request = self
+            .table
+            .get_mut(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        let path_and_query = match request.uri().query() {
+            Some(query) => format!("{}?{}", path, query),
+            None => path,
+        };
+        let path_and_query = PathAndQuery::from_str(&path_and_query)
+            .map_err(|err| Error::InvalidUri(format!("Error assigning path: {}", err)))?;
+        modify_uri(request, &self.quotas, |parts| {
+            parts.path_and_query = Some(path_and_query);
+            Ok(())
+        })
+    }
+
+    fn query(&mut self, self_: Resource<Request>) -> Result<Option<String>, Error> {
+        let request = self
+            .table
+            .get(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        Ok(request.uri().query().map(str::to_string))
+    }
+
+    fn set_query(&mut self, self_: Resource<Request>, query: Option<String>) -> Result<(), Error> {
+        if query.as_ref().is_some_and(|query| query.contains('#')) {
+            return Err(Error::InvalidUri("Query must not contain `#`".to_string()));
+        }
+        let request = self
+            .table
+            .get_mut(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        let path = request.uri().path();
+        let path_and_query = match query {
+            Some(query) => format!("{}?{}", path, query),
+            None => path.to_string(),
+        };
+        let path_and_query = PathAndQuery::from_str(&path_and_query)
+            .map_err(|err| Error::InvalidUri(format!("Error assigning query: {}", err)))?;
+        modify_uri(request, &self.quotas, |parts| {
+            parts.path_and_query = Some(path_and_query);
+            Ok(())
+        })
+    }
+
+    fn body(
+        &mut self,
+        self_: Resource<Request>,
+        max_size: u64,
+    ) -> wasmtime::Result<Result<Vec<u8>, Error>> {
+        // Blocks until the body has arrived, buffering at most `max_size` bytes,
+        // and traps once the deadline has passed.
+        let body = match self.shared_body(&self_) {
+            Ok(body) => body,
+            Err(error) => return Ok(Err(error)),
+        };
+        body.read_all_blocking(max_size, self.deadline)
+    }
+
+    fn body_stream(&mut self, self_: Resource<Request>) -> Result<Resource<DynInputStream>, Error> {
+        self.count_resource()?;
+        let stream: DynInputStream =
+            Box::new(BodyStream::new(self.shared_body(&self_)?, self.deadline));
+        self.table
+            .push(stream)
+            .map_err(|err| Error::Internal(err.to_string()))
+    }
+
+    fn set_body(&mut self, self_: Resource<Request>, body: Vec<u8>) -> Result<(), Error> {
+        self.quotas.check_body(&body)?;
+        let request = self
+            .table
+            .get_mut(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        if request.headers().contains_key(header::CONTENT_LENGTH) {
+            request
+                .headers_mut()
+                .insert(header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+        }
+        *request.body_mut() = Body::from(body);
+        self.body = None;
+        Ok(())
+    }
+
     fn drop(&mut self, rep: Resource<Request>) -> wasmtime::Result<()> {
+        self.table.delete(rep)?;
         Ok(())
     }
 }
 
+impl HostResponse for ComponentRunStates {
+    fn new(&mut self) -> wasmtime::Result<Resource<Response>> {
+        self.count_resource()?;
+        Ok(self.table.push(RamaResponse::new(Body::empty()))?)
+    }
+
+    fn status(&mut self, self_: Resource<Response>) -> Result<u16, Error> {
+        let response = self
+            .table
+            .get(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        Ok(response.status().as_u16())
+    }
+
+    fn set_status(&mut self, self_: Resource<Response>, status: u16) -> Result<(), Error> {
+        let status = StatusCode::from_u16(status)
+            .map_err(|err| Error::InvalidStatus(format!("Error assigning status: {}", err)))?;
+        let response = self
+            .table
+            .get_mut(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        *response.status_mut() = status;
+        Ok(())
+    }
+
+    fn headers(&mut self, self_: Resource<Response>) -> Result<Vec<(String, Vec<u8>)>, Error> {
+        let response = self
+            .table
+            .get(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        let header = response
+            .headers()
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.as_bytes().to_vec()))
+            .collect();
+        Ok(header)
+    }
+
+    fn set_header(
+        &mut self,
+        self_: Resource<Response>,
+        key: String,
+        value: Vec<u8>,
+    ) -> Result<(), Error> {
+        let header_key =
+            HeaderName::from_str(&key).map_err(|err| Error::InvalidHeaderName(err.to_string()))?;
+        let header_value = HeaderValue::from_bytes(&value)
+            .map_err(|err| Error::InvalidHeaderValue(err.to_string()))?;
+        let headers = self
+            .table
+            .get_mut(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?
+            .headers_mut();
+        self.quotas
+            .check_headers(headers, Some(&header_key), (&header_key, &header_value))?;
+        headers.insert(header_key, header_value);
+        Ok(())
+    }
+
+    fn set_body(&mut self, self_: Resource<Response>, body: Vec<u8>) -> Result<(), Error> {
+        self.quotas.check_body(&body)?;
+        let response = self
+            .table
+            .get_mut(&self_)
+            .map_err(|err| Error::NotFound(err.to_string()))?;
+        *response.body_mut() = Body::from(body);
+        Ok(())
+    }
+
+    fn drop(&mut self, rep: Resource<Response>) -> wasmtime::Result<()> {
+        self.table.delete(rep)?;
+        Ok(())
+    }
+}
+
+/// Builds the error for `component`, which cannot be used as a router because
+/// of `source`.
+fn not_a_router(
+    engine: &Engine,
+    component: &Component,
+    name: &str,
+    version: &Version,
+    source: anyhow::Error,
+) -> LoadError {
+    let component_type = component.component_type();
+    LoadError::NotARouter {
+        name: name.to_string(),
+        version: version.clone(),
+        imports: component_type
+            .imports(engine)
+            .map(|(name, _)| name.to_string())
+            .collect(),
+        exports: component_type
+            .exports(engine)
+            .map(|(name, _)| name.to_string())
+            .collect(),
+        source,
+    }
+}
+
+/// Lists the `.wasm` files in `dir`, ordered by path.
+fn wasm_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Cannot read directory `{}`", dir.display()))?;
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_file()
+            && path
+                .extension()
+                .is_some_and(|extension| extension == "wasm")
+        {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Reads the name and version from a file named `name@version.wasm`.
+fn name_and_version(path: &Path) -> Result<(String, Version)> {
+    let Some((name, version)) = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.split_once('@'))
+        .filter(|(name, _)| !name.is_empty())
+    else {
+        anyhow::bail!("`{}` is not named `name@version.wasm`", path.display());
+    };
+    let version = version
+        .parse()
+        .with_context(|| format!("`{}` has an invalid version", path.display()))?;
+    Ok((name.to_string(), version))
+}
+
+/// Epoch deadline used when a call has no deadline, far enough in the future to
+/// never be reached without overflowing the engine's epoch counter.
+const NO_DEADLINE: u64 = u64::MAX / 2;
+
+/// Units of fuel after which an async call yields to the executor.
+const FUEL_YIELD_INTERVAL: u64 = 10_000;
+
+/// A router prepared for the synchronous or the asynchronous call path.
+enum RouterPre {
+    Sync(CrossroadsPre<ComponentRunStates>),
+    Async(asynchronous::CrossroadsPre<ComponentRunStates>),
+}
+
+/// A router instantiated for the synchronous or the asynchronous call path.
+enum Router {
+    Sync(Crossroads),
+    Async(asynchronous::Crossroads),
+}
+
+/// Settings of a router instance, applied to a pooled router when a call begins.
+#[derive(Clone)]
+struct InstanceSettings {
+    fuel_budget: Option<u64>,
+    deadline: Option<Duration>,
+    limits: InstanceLimits,
+    wasi: WasiConfig,
+}
+
+/// A compiled component and what the registry records about it.
+struct Loaded {
+    component: Component,
+    hash: String,
+    source: Option<Source>,
+}
+
+/// Reads and compiles components for the engine of a runtime. It is cheap to
+/// clone, so async callers can hand it to a blocking thread.
+#[derive(Clone)]
+struct Compiler {
+    engine: Engine,
+    cache: Option<ComponentCache>,
+}
+
+impl Compiler {
+    /// Compiles the component at `path_to_component`, or loads it from the
+    /// cache if one is configured.
+    fn load(&self, path_to_component: &Path) -> Result<Loaded> {
+        let display = path_to_component.display();
+        let source = Source::new(path_to_component, false);
+        let wasm = std::fs::read(path_to_component)
+            .with_context(|| format!("Cannot read component `{}`", display))?;
+        if self.engine.detect_precompiled(&wasm).is_some() {
+            anyhow::bail!(
+                "`{}` is precompiled, add it with `add_precompiled` instead",
+                display
+            );
+        }
+        let loaded = self
+            .compile(&wasm)
+            .with_context(|| format!("Cannot load component `{}`", display))?;
+        Ok(Loaded {
+            source: Some(source),
+            ..loaded
+        })
+    }
+
+    /// Compiles `wasm`, or loads it from the cache if one is configured.
+    fn compile(&self, wasm: &[u8]) -> Result<Loaded> {
+        let hash = format!("{:x}", Sha256::digest(wasm));
+        let component = match &self.cache {
+            Some(cache) => cache.load(&self.engine, wasm, &hash)?,
+            None => Component::new(&self.engine, wasm)?,
+        };
+        Ok(Loaded {
+            component,
+            hash,
+            source: None,
+        })
+    }
+
+    /// Loads the artifact at `path_to_artifact`, refusing anything but a
+    /// component precompiled for a compatible engine.
+    ///
+    /// # Safety
+    ///
+    /// See [`Runtime::add_precompiled`].
+    unsafe fn load_precompiled(&self, path_to_artifact: &Path) -> Result<Loaded> {
+        let display = path_to_artifact.display();
+        let source = Source::new(path_to_artifact, true);
+        let artifact = std::fs::read(path_to_artifact)
+            .with_context(|| format!("Cannot read `{}`", display))?;
+        match self.engine.detect_precompiled(&artifact) {
+            Some(Precompiled::Component) => {}
+            Some(Precompiled::Module) => {
+                anyhow::bail!(
+                    "`{}` is a precompiled core module, not a component",
+                    display
+                )
+            }
+            None => anyhow::bail!("`{}` is not a precompiled component", display),
+        }
+        // SAFETY: upheld by the caller. Wasmtime checks that the artifact was
+        // built by the same version with compatible settings.
+        let component = unsafe { Component::deserialize(&self.engine, &artifact) }
+            .with_context(|| format!("Cannot use `{}` with this runtime's engine", display))?;
+        Ok(Loaded {
+            component,
+            hash: format!("{:x}", Sha256::digest(&artifact)),
+            source: Some(source),
+        })
+    }
+}
+
+struct RouterInstance {
+    name: String,
+    version: Version,
+    /// Hex encoded SHA-256 of the wasm or precompiled artifact.
+    hash: String,
+    router_pre: RouterPre,
+    settings: Mutex<InstanceSettings>,
+    stats: Mutex<InstanceStats>,
+    /// The routers shared by all calls, or `None` if every call gets a fresh one.
+    pool: Option<Pool>,
+    /// The file the component was loaded from.
+    source: Option<Source>,
+}
+
+impl RouterInstance {
+    fn new(
+        name: &str,
+        version: Version,
+        loaded: Loaded,
+        settings: InstanceSettings,
+        router_pre: RouterPre,
+        pool: Option<Pool>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            version,
+            hash: loaded.hash,
+            router_pre,
+            settings: Mutex::new(settings),
+            stats: Mutex::new(InstanceStats::default()),
+            pool,
+            source: loaded.source,
+        }
+    }
+
+    fn info(&self, id: usize) -> ComponentInfo {
+        ComponentInfo {
+            id,
+            name: self.name.clone(),
+            version: self.version.clone(),
+            path: self.source.as_ref().map(|source| source.path.clone()),
+            hash: self.hash.clone(),
+        }
+    }
+
+    /// Arms the fuel budget and deadline for the next call and lends `request`
+    /// to the store of `pooled`. Returns the resource handed to the router and
+    /// its fuel.
+    fn begin_call(
+        &self,
+        config: &RuntimeConfig,
+        pooled: &mut PooledRouter,
+        request: RamaRequest,
+    ) -> Result<(Resource<Request>, u64), HandleError> {
+        self.stats.lock().unwrap().calls += 1;
+        let settings = self.settings.lock().unwrap();
+        pooled.store.data_mut().limiter.limits = settings.limits.clone();
+        pooled.store.data_mut().resources_created = 0;
+        pooled.store.data_mut().deadline = None;
+        let fuel = settings.fuel_budget.unwrap_or(u64::MAX);
+        if config.consume_fuel {
+            pooled.store.set_fuel(fuel)?;
+        }
+        if let Some(tick) = config.epoch_tick {
+            // The epoch may advance right after the deadline is set, so one
+            // more tick keeps calls from being cut short.
+            let ticks = match settings.deadline {
+                Some(deadline) => (deadline.as_nanos().div_ceil(tick.as_nanos()) + 1)
+                    .min(NO_DEADLINE.into()) as u64,
+                None => NO_DEADLINE,
+            };
+            // Host calls wait for as many ticks as the component may run.
+            if settings.deadline.is_some() {
+                pooled.store.data_mut().deadline = u32::try_from(ticks)
+                    .ok()
+                    .and_then(|ticks| Instant::now().checked_add(tick * ticks));
+            }
+            if config.async_support {
+                // Async calls yield to the executor on every tick, and only
+                // trap once the deadline has passed.
+                let mut remaining = ticks;
+                pooled.store.epoch_deadline_callback(move |_| {
+                    remaining -= 1;
+                    if remaining == 0 {
+                        Err(Trap::Interrupt.into())
+                    } else {
+                        Ok(UpdateDeadline::Yield(1))
+                    }
+                });
+                pooled.store.set_epoch_deadline(1);
+            } else {
+                pooled.store.set_epoch_deadline(ticks);
+            }
+        }
+        drop(settings);
+        // The component only borrows the request, so it stays in the table until
+        // `finish_call` takes it back out, whether the call succeeded, failed or trapped.
+        let resource = pooled.store.data_mut().table.push(request)?;
+        Ok((resource, fuel))
+    }
+
+    /// Takes the request back from the store and turns the router's outcome
+    /// into a [`Handled`]. Unless the call trapped, the router is marked clean
+    /// so it returns to the pool.
+    fn finish_call(
+        &self,
+        config: &RuntimeConfig,
+        pooled: &mut Checkout<'_>,
+        resource: Resource<Request>,
+        fuel: u64,
+        violations: u64,
+        call: wasmtime::Result<Result<Outcome, Error>>,
+    ) -> Result<Handled, HandleError> {
+        let fuel_consumed = if config.consume_fuel {
+            Some(fuel - pooled.store.get_fuel()?)
+        } else {
+            None
+        };
+        let mut stats = self.stats.lock().unwrap();
+        stats.limit_violations += pooled.store.data().limiter.violations - violations;
+        stats.fuel_consumed += fuel_consumed.unwrap_or(0);
+        let mut rama_request = pooled.store.data_mut().table.delete(resource)?;
+        if let Some(body) = pooled.store.data_mut().body.take() {
+            // The rest of a partly streamed body no longer has the announced length.
+            if body.is_consumed() {
+                rama_request.headers_mut().remove(header::CONTENT_LENGTH);
+            }
+        }
+        let result = match call {
+            Ok(result) => result,
+            Err(error) => {
+                stats.traps += 1;
+                return Err(error.into());
+            }
+        };
+        drop(stats);
+        pooled.set_clean();
+        let routed = match result? {
+            Outcome::Continue => Routed::Forward(rama_request),
+            Outcome::Respond(response) => {
+                let rama_response = pooled.store.data_mut().table.delete(response)?;
+                Routed::Respond(rama_response)
+            }
+        };
+        Ok(Handled {
+            routed,
+            fuel_consumed,
+        })
+    }
+}
+
+/// Runs router components. The runtime is `Send + Sync`, so it can be shared
+/// between threads or tasks, and every instance handles up to
+/// [`RuntimeConfig::pool_size`] calls concurrently, or any number of calls
+/// with [`RuntimeConfig::isolate_calls`].
 pub struct Runtime {
+    config: RuntimeConfig,
     engine: Engine,
     linker: Linker<ComponentRunStates>,
-    store: Store<ComponentRunStates>,
-    instances: HashMap<usize, Router>,
+    compiler: Compiler,
+    next_id: AtomicUsize,
+    instances: RwLock<Registry>,
+    /// Whether [`Runtime::watch`] was called.
+    watching: AtomicBool,
 }
 
 impl Runtime {
-    pub fn new() -> Result<Self> {
-        let engine = wasmtime::Engine::default();
+    pub fn new(config: RuntimeConfig) -> Result<Self> {
+        if config.pool_size == 0 && !config.isolate_calls {
+            anyhow::bail!("The pool size must be at least 1");
+        }
+        if config.epoch_tick.is_some_and(|tick| tick.is_zero()) {
+            anyhow::bail!("The epoch tick must be longer than zero");
+        }
+        let mut engine_config = wasmtime::Config::new();
+        engine_config.consume_fuel(config.consume_fuel);
+        engine_config.epoch_interruption(config.epoch_tick.is_some());
+        engine_config.async_support(config.async_support);
+        if let Some(pooling) = &config.pooling {
+            engine_config.allocation_strategy(pooling.allocation_config());
+        }
+        let engine = Engine::new(&engine_config)?;
+        if let Some(tick) = config.epoch_tick {
+            // The ticker only holds a weak reference, so it stops once the
+            // runtime and everything else using the engine is dropped.
+            let engine = engine.weak();
+            std::thread::spawn(move || {
+                while let Some(engine) = engine.upgrade() {
+                    engine.increment_epoch();
+                    drop(engine);
+                    std::thread::sleep(tick);
+                }
+            });
+        }
         let mut linker = Linker::new(&engine);
-        wasmtime_wasi::p2::add_to_linker_sync(&mut linker)?;
-        wit::crossroads::types::add_to_linker(&mut linker, |state| state)?;
-        let wasi = WasiCtxBuilder::new().inherit_stdio().inherit_args().build();
-        let state = ComponentRunStates {
-            wasi_ctx: wasi,
-            table: ResourceTable::new(),
-            requests: HashMap::new(),
+        if config.async_support {
+            wasmtime_wasi::p2::add_to_linker_async(&mut linker)?;
+        } else {
+            wasmtime_wasi::p2::add_to_linker_sync(&mut linker)?;
+        }
+        if config.async_support {
+            asynchronous::wit::crossroads::types::add_to_linker(&mut linker, |state| state)?;
+        } else {
+            wit::crossroads::types::add_to_linker(&mut linker, |state| state)?;
+        }
+        let compiler = Compiler {
+            engine: engine.clone(),
+            cache: config.cache_dir.as_ref().map(ComponentCache::new),
         };
-        let store = Store::new(&engine, state);
-        let instances = HashMap::new();
+        let instances = RwLock::new(Registry::default());
         let runtime = Self {
+            config,
             engine,
             linker,
-            store,
+            compiler,
+            next_id: AtomicUsize::new(1),
             instances,
+            watching: AtomicBool::new(false),
         };
         Ok(runtime)
     }
 
-    pub fn add_instance(&mut self, path_to_component: impl AsRef<Path>) -> Result<usize> {
-        let id = self.instances.keys().max().unwrap_or(&0) + 1;
+    /// Adds the component at `path_to_component` as `name@version`, with
+    /// access to the host as granted by `wasi`.
+    pub fn add_instance(
+        &self,
+        name: &str,
+        version: Version,
+        path_to_component: impl AsRef<Path>,
+        wasi: WasiConfig,
+    ) -> Result<usize> {
+        let loaded = self.compiler.load(path_to_component.as_ref())?;
+        let instance = self.build_instance(name, version, loaded, self.default_settings(wasi))?;
+        self.insert_instance(instance)
+    }
+
+    /// Async version of [`Runtime::add_instance`]. The component is read and
+    /// compiled on Tokio's blocking threads, so this has to run on Tokio.
+    pub async fn add_instance_async(
+        &self,
+        name: &str,
+        version: Version,
+        path_to_component: impl AsRef<Path>,
+        wasi: WasiConfig,
+    ) -> Result<usize> {
+        let path_to_component = path_to_component.as_ref().to_path_buf();
+        let loaded = self
+            .load_blocking(move |compiler| compiler.load(&path_to_component))
+            .await?;
+        let instance = self
+            .build_instance_async(name, version, loaded, self.default_settings(wasi))
+            .await?;
+        self.insert_instance(instance)
+    }
+
+    /// Adds the component `wasm`, e.g. embedded with `include_bytes!`, as
+    /// `name@version`.
+    pub fn add_instance_from_bytes(
+        &self,
+        name: &str,
+        version: Version,
+        wasm: &[u8],
+        wasi: WasiConfig,
+    ) -> Result<usize> {
+        let loaded = self
+            .compiler
+            .compile(wasm)
+            .with_context(|| format!("Cannot load component {}@{}", name, version))?;
+        let instance = self.build_instance(name, version, loaded, self.default_settings(wasi))?;
+        self.insert_instance(instance)
+    }
+
+    /// Async version of [`Runtime::add_instance_from_bytes`], which compiles on
+    /// Tokio's blocking threads.
+    pub async fn add_instance_from_bytes_async(
+        &self,
+        name: &str,
+        version: Version,
+        wasm: &[u8],
+        wasi: WasiConfig,
+    ) -> Result<usize> {
+        let wasm = wasm.to_vec();
+        let loaded = self
+            .load_blocking(move |compiler| compiler.compile(&wasm))
+            .await
+            .with_context(|| format!("Cannot load component {}@{}", name, version))?;
+        let instance = self
+            .build_instance_async(name, version, loaded, self.default_settings(wasi))
+            .await?;
+        self.insert_instance(instance)
+    }
+
+    /// Adds every `.wasm` file in `dir`, each named `name@version.wasm`, with
+    /// the same `wasi` settings. A file without a version fails to load rather
+    /// than getting a made up one. Returns the outcome for each file, ordered
+    /// by path, and only fails as a whole if `dir` cannot be read.
+    pub fn add_instances_from_dir(
+        &self,
+        dir: impl AsRef<Path>,
+        wasi: WasiConfig,
+    ) -> Result<Vec<(PathBuf, Result<usize>)>> {
+        let mut outcomes = Vec::new();
+        for path in wasm_files(dir.as_ref())? {
+            let outcome = name_and_version(&path)
+                .and_then(|(name, version)| self.add_instance(&name, version, &path, wasi.clone()));
+            outcomes.push((path, outcome));
+        }
+        Ok(outcomes)
+    }
+
+    /// Async version of [`Runtime::add_instances_from_dir`].
+    pub async fn add_instances_from_dir_async(
+        &self,
+        dir: impl AsRef<Path>,
+        wasi: WasiConfig,
+    ) -> Result<Vec<(PathBuf, Result<usize>)>> {
+        let mut outcomes = Vec::new();
+        for path in wasm_files(dir.as_ref())? {
+            let outcome = match name_and_version(&path) {
+                Ok((name, version)) => {
+                    self.add_instance_async(&name, version, &path, wasi.clone())
+                        .await
+                }
+                Err(error) => Err(error),
+            };
+            outcomes.push((path, outcome));
+        }
+        Ok(outcomes)
+    }
+
+    /// Compiles the component at `path_to_component` for this runtime's engine
+    /// settings, to be loaded later by [`Runtime::add_precompiled`].
+    pub fn precompile(&self, path_to_component: impl AsRef<Path>) -> Result<Vec<u8>> {
+        let path_to_component = path_to_component.as_ref();
+        let wasm = std::fs::read(path_to_component)
+            .with_context(|| format!("Cannot read `{}`", path_to_component.display()))?;
+        self.engine
+            .precompile_component(&wasm)
+            .with_context(|| format!("Cannot compile `{}`", path_to_component.display()))
+    }
+
+    /// Adds a component compiled by [`Runtime::precompile`] as `name@version`.
+    /// Artifacts built by another wasmtime version or with engine settings
+    /// incompatible with this runtime are refused.
+    ///
+    /// # Safety
+    ///
+    /// The artifact is mapped and run as native code, and only its header is
+    /// checked. It must be the unmodified output of [`Runtime::precompile`].
+    pub unsafe fn add_precompiled(
+        &self,
+        name: &str,
+        version: Version,
+        path_to_artifact: impl AsRef<Path>,
+        wasi: WasiConfig,
+    ) -> Result<usize> {
+        // SAFETY: upheld by the caller.
+        let loaded = unsafe { self.compiler.load_precompiled(path_to_artifact.as_ref())? };
+        let instance = self.build_instance(name, version, loaded, self.default_settings(wasi))?;
+        self.insert_instance(instance)
+    }
 
-        let component = Component::from_file(&self.engine, path_to_component)?;
-        let instance = self.linker.instantiate(&mut self.store, &component)?;
+    /// Async version of [`Runtime::add_precompiled`], which reads the artifact
+    /// on Tokio's blocking threads.
+    ///
+    /// # Safety
+    ///
+    /// See [`Runtime::add_precompiled`].
+    pub async unsafe fn add_precompiled_async(
+        &self,
+        name: &str,
+        version: Version,
+        path_to_artifact: impl AsRef<Path>,
+        wasi: WasiConfig,
+    ) -> Result<usize> {
+        let path_to_artifact = path_to_artifact.as_ref().to_path_buf();
+        let loaded = self
+            .load_blocking(move |compiler| {
+                // SAFETY: upheld by the caller.
+                unsafe { compiler.load_precompiled(&path_to_artifact) }
+            })
+            .await?;
+        let instance = self
+            .build_instance_async(name, version, loaded, self.default_settings(wasi))
+            .await?;
+        self.insert_instance(instance)
+    }
 
-        let interface_namespace = "wit:crossroads/router@0.1.0";
-        let interface_idx = instance
-            .get_export_index(&mut self.store, None, interface_namespace)
-            .expect(&format!("Cannot get `{}` interface", interface_namespace));
+    /// Prepares the `loaded` component as a router and fills its pool.
+    fn build_instance(
+        &self,
+        name: &str,
+        version: Version,
+        loaded: Loaded,
+        settings: InstanceSettings,
+    ) -> Result<RouterInstance> {
+        if self.config.async_support {
+            anyhow::bail!("The runtime is async, add instances with the async methods instead");
+        }
+        let router_pre = self.prepare(&loaded.component, name, &version)?;
+        let pool = if self.config.isolate_calls {
+            None
+        } else {
+            let routers = (0..self.config.pool_size)
+                .map(|_| self.instantiate(&router_pre, &settings))
+                .collect::<Result<Vec<_>>>()?;
+            Some(Pool::new(routers))
+        };
+        Ok(RouterInstance::new(
+            name, version, loaded, settings, router_pre, pool,
+        ))
+    }
 
-        let parent_export_idx = Some(&interface_idx);
-        let func_id_handle_request = instance
-            .get_export_index(&mut self.store, parent_export_idx, "handle")
-            .expect(&format!("Cannot get `{}` function", "handle"));
+    /// Async version of [`Runtime::build_instance`].
+    async fn build_instance_async(
+        &self,
+        name: &str,
+        version: Version,
+        loaded: Loaded,
+        settings: InstanceSettings,
+    ) -> Result<RouterInstance> {
+        if !self.config.async_support {
+            anyhow::bail!("The runtime is not async, add instances with the sync methods instead");
+        }
+        let router_pre = self.prepare(&loaded.component, name, &version)?;
+        let pool = if self.config.isolate_calls {
+            None
+        } else {
+            let mut routers = Vec::with_capacity(self.config.pool_size);
+            for _ in 0..self.config.pool_size {
+                routers.push(self.instantiate_async(&router_pre, &settings).await?);
+            }
+            Some(Pool::new(routers))
+        };
+        Ok(RouterInstance::new(
+            name, version, loaded, settings, router_pre, pool,
+        ))
+    }
 
-        let func_handle_request = instance
-            .get_func(&mut self.store, func_id_handle_request)
-            .expect("Unreachable since we've got func_idx");
+    /// Links `component` and checks that it is a router.
+    fn prepare(&self, component: &Component, name: &str, version: &Version) -> Result<RouterPre> {
+        let not_a_router =
+            |source: anyhow::Error| not_a_router(&self.engine, component, name, version, source);
+        let instance_pre = self
+            .linker
+            .instantiate_pre(component)
+            .map_err(not_a_router)?;
+        let router_pre = if self.config.async_support {
+            asynchronous::CrossroadsPre::new(instance_pre).map(RouterPre::Async)
+        } else {
+            CrossroadsPre::new(instance_pre).map(RouterPre::Sync)
+        };
+        Ok(router_pre.map_err(not_a_router)?)
+    }
 
-        let handle = func_handle_request
-            .typed::<(Resource<Request>,), (Result<(), String>,)>(&self.store)?;
+    fn default_settings(&self, wasi: WasiConfig) -> InstanceSettings {
+        InstanceSettings {
+            fuel_budget: None,
+            deadline: None,
+            limits: self.config.instance_limits.clone(),
+            wasi,
+        }
+    }
 
-        self.instances.insert(id, handle);
+    /// Runs `load` on Tokio's blocking threads, so reading and compiling a
+    /// component does not hold up the executor.
+    async fn load_blocking(
+        &self,
+        load: impl FnOnce(&Compiler) -> Result<Loaded> + Send + 'static,
+    ) -> Result<Loaded> {
+        let compiler = self.compiler.clone();
+        tokio::task::spawn_blocking(move || load(&compiler)).await?
+    }
 
+    /// Registers `instance` under a new id. Ids are never reused, even after
+    /// an instance is removed.
+    fn insert_instance(&self, instance: RouterInstance) -> Result<usize> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.instances.write().unwrap().insert(id, instance)?;
         Ok(id)
     }
 
-    pub fn call_handle(&mut self, id: usize, request: RamaRequest) -> Result<RamaRequest> {
-        let resource = self.store.data_mut().table.push(())?;
-        let resource_id = resource.rep();
-        self.store.data_mut().requests.insert(resource_id, request);
-        let Some(router) = self.instances.get(&id) else {
-            anyhow::bail!("Couldn't find function with id {}", id);
+    /// Removes the instance `id`. Calls already running on it still finish,
+    /// later calls fail with [`HandleError::UnknownInstance`].
+    pub fn remove_instance(&self, id: usize) -> Result<()> {
+        if self.instances.write().unwrap().remove(id).is_none() {
+            anyhow::bail!("Couldn't find instance with id {}", id);
+        }
+        Ok(())
+    }
+
+    /// Replaces the instance `id` by the component at `path_to_component`,
+    /// keeping its name, version, fuel budget, deadline and limits but starting
+    /// its stats over. Calls already running on the old component finish on
+    /// it, later calls go to the new one. If the new component cannot be
+    /// loaded, the old one stays in place. Precompiled artifacts are refused,
+    /// replace the instance by removing it and using
+    /// [`Runtime::add_precompiled`] instead.
+    pub fn replace_instance(&self, id: usize, path_to_component: impl AsRef<Path>) -> Result<()> {
+        let Some(current) = self.instance(id) else {
+            anyhow::bail!("Couldn't find instance with id {}", id);
+        };
+        let loaded = self.compiler.load(path_to_component.as_ref())?;
+        let settings = current.settings.lock().unwrap().clone();
+        let instance =
+            self.build_instance(&current.name, current.version.clone(), loaded, settings)?;
+        self.instances.write().unwrap().replace(id, instance)
+    }
+
+    /// Async version of [`Runtime::replace_instance`], which reads and compiles
+    /// on Tokio's blocking threads.
+    pub async fn replace_instance_async(
+        &self,
+        id: usize,
+        path_to_component: impl AsRef<Path>,
+    ) -> Result<()> {
+        let Some(current) = self.instance(id) else {
+            anyhow::bail!("Couldn't find instance with id {}", id);
+        };
+        let path_to_component = path_to_component.as_ref().to_path_buf();
+        let loaded = self
+            .load_blocking(move |compiler| compiler.load(&path_to_component))
+            .await?;
+        let settings = current.settings.lock().unwrap().clone();
+        let instance = self
+            .build_instance_async(&current.name, current.version.clone(), loaded, settings)
+            .await?;
+        self.instances.write().unwrap().replace(id, instance)
+    }
+
+    /// Returns the id of the component `name@version`, where the version may
+    /// be `latest`. A bare `name` refers to the latest version as well.
+    pub fn resolve(&self, reference: &str) -> Result<usize> {
+        self.instances.read().unwrap().resolve(reference)
+    }
+
+    /// Lists all loaded components, ordered by name and version.
+    pub fn components(&self) -> Vec<ComponentInfo> {
+        let mut components = self
+            .instances
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(id, instance)| instance.info(id))
+            .collect::<Vec<_>>();
+        components.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+        components
+    }
+
+    fn instance(&self, id: usize) -> Option<Arc<RouterInstance>> {
+        self.instances.read().unwrap().get(id).cloned()
+    }
+
+    /// Limits every call to the instance `id` to `fuel` units of fuel, or lifts
+    /// the limit if `fuel` is `None`.
+    pub fn set_fuel_budget(&self, id: usize, fuel: Option<u64>) -> Result<()> {
+        if !self.config.consume_fuel {
+            anyhow::bail!("Fuel consumption is not enabled in the runtime config");
+        }
+        let Some(instance) = self.instance(id) else {
+            anyhow::bail!("Couldn't find instance with id {}", id);
+        };
+        instance.settings.lock().unwrap().fuel_budget = fuel;
+        Ok(())
+    }
+
+    /// Interrupts every call to the instance `id` that runs longer than
+    /// `deadline`, or lifts the limit if `deadline` is `None`. Time the router
+    /// spends waiting for the request body counts as well.
+    pub fn set_deadline(&self, id: usize, deadline: Option<Duration>) -> Result<()> {
+        if self.config.epoch_tick.is_none() {
+            anyhow::bail!("Epoch interruption is not enabled in the runtime config");
+        }
+        let Some(instance) = self.instance(id) else {
+            anyhow::bail!("Couldn't find instance with id {}", id);
+        };
+        instance.settings.lock().unwrap().deadline = deadline;
+        Ok(())
+    }
+
+    /// Replaces the [`InstanceLimits`] of the instance `id`. Already allocated
+    /// memories and tables are kept, the new limits apply to further growth
+    /// from the next call on.
+    pub fn set_limits(&self, id: usize, limits: InstanceLimits) -> Result<()> {
+        let Some(instance) = self.instance(id) else {
+            anyhow::bail!("Couldn't find instance with id {}", id);
+        };
+        instance.settings.lock().unwrap().limits = limits;
+        Ok(())
+    }
+
+    pub fn stats(&self, id: usize) -> Option<InstanceStats> {
+        self.instance(id)
+            .map(|instance| instance.stats.lock().unwrap().clone())
+    }
+
+    /// Creates a fresh store with the limits and WASI context of `settings`,
+    /// ready to instantiate a router.
+    fn new_store(&self, settings: &InstanceSettings) -> Result<Store<ComponentRunStates>> {
+        let state = ComponentRunStates {
+            wasi_ctx: settings.wasi.build()?,
+            table: ResourceTable::new(),
+            limiter: Limiter::new(settings.limits.clone()),
+            quotas: self.config.request_quotas.clone(),
+            body: None,
+            resources_created: 0,
+            deadline: None,
+        };
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limiter);
+        // Instantiation is neither subject to the per-call budget nor deadline.
+        if self.config.consume_fuel {
+            store.set_fuel(u64::MAX)?;
+            if self.config.async_support {
+                store.fuel_async_yield_interval(Some(FUEL_YIELD_INTERVAL))?;
+            }
+        }
+        if self.config.epoch_tick.is_some() {
+            store.set_epoch_deadline(NO_DEADLINE);
+        }
+        Ok(store)
+    }
+
+    /// Instantiates `router_pre` in a fresh store set up by `settings`.
+    fn instantiate(
+        &self,
+        router_pre: &RouterPre,
+        settings: &InstanceSettings,
+    ) -> Result<PooledRouter> {
+        let mut store = self.new_store(settings)?;
+        let router = match router_pre {
+            RouterPre::Sync(router_pre) => Router::Sync(router_pre.instantiate(&mut store)?),
+            RouterPre::Async(_) => {
+                anyhow::bail!("Cannot instantiate an async router synchronously")
+            }
+        };
+        Ok(PooledRouter { store, router })
+    }
+
+    /// Async version of [`Runtime::instantiate`].
+    async fn instantiate_async(
+        &self,
+        router_pre: &RouterPre,
+        settings: &InstanceSettings,
+    ) -> Result<PooledRouter> {
+        let mut store = self.new_store(settings)?;
+        let router = match router_pre {
+            RouterPre::Async(router_pre) => {
+                Router::Async(router_pre.instantiate_async(&mut store).await?)
+            }
+            RouterPre::Sync(_) => anyhow::bail!("Cannot instantiate a sync router asynchronously"),
+        };
+        Ok(PooledRouter { store, router })
+    }
+
+    /// Calls the router `id` on the calling thread. If all routers of the
+    /// instance are busy, waits for one to become free. With
+    /// [`RuntimeConfig::isolate_calls`] the call gets a router of its own.
+    ///
+    /// The router's `body` and reads from `body-stream` block the thread
+    /// until the request body has arrived.
+    pub fn call_handle(&self, id: usize, request: RamaRequest) -> Result<Handled, HandleError> {
+        let Some(instance) = self.instance(id) else {
+            return Err(HandleError::UnknownInstance(id));
+        };
+        if !matches!(instance.router_pre, RouterPre::Sync(_)) {
+            return Err(HandleError::WrongCallMode(
+                "The runtime is async, use `call_handle_async` instead",
+            ));
+        }
+        let mut pooled = match &instance.pool {
+            Some(pool) => pool.checkout(self.config.pool_timeout)?,
+            None => {
+                let settings = instance.settings.lock().unwrap().clone();
+                Checkout::fresh(self.instantiate(&instance.router_pre, &settings)?)
+            }
+        };
+        if pooled.is_vacant() {
+            let settings = instance.settings.lock().unwrap().clone();
+            pooled.fill(self.instantiate(&instance.router_pre, &settings)?);
+        }
+        let (resource, fuel) = instance.begin_call(&self.config, &mut pooled, request)?;
+        let violations = pooled.store.data().limiter.violations;
+        let PooledRouter { store, router } = &mut *pooled;
+        let Router::Sync(router) = router else {
+            unreachable!("checked above");
+        };
+        let call = router
+            .wit_crossroads_router()
+            .call_handle(store, Resource::new_borrow(resource.rep()));
+        // A router that trapped stays dirty, so it is discarded and the next
+        // call instantiates a fresh one in its place.
+        instance.finish_call(&self.config, &mut pooled, resource, fuel, violations, call)
+    }
+
+    /// Calls the router `id` without blocking the executor. The router's
+    /// `body` waits for the request body to arrive, and the router yields to
+    /// the executor every epoch tick or 10 000 units of fuel.
+    pub async fn call_handle_async(
+        &self,
+        id: usize,
+        request: RamaRequest,
+    ) -> Result<Handled, HandleError> {
+        let Some(instance) = self.instance(id) else {
+            return Err(HandleError::UnknownInstance(id));
+        };
+        if !matches!(instance.router_pre, RouterPre::Async(_)) {
+            return Err(HandleError::WrongCallMode(
+                "The runtime is not async, use `call_handle` instead",
+            ));
+        }
+        let mut pooled = match &instance.pool {
+            Some(pool) => pool.checkout_async(self.config.pool_timeout).await?,
+            None => {
+                let settings = instance.settings.lock().unwrap().clone();
+                Checkout::fresh(
+                    self.instantiate_async(&instance.router_pre, &settings)
+                        .await?,
+                )
+            }
         };
-        let (result,) = router.call(&mut self.store, (resource,))?;
-        result.map_err(|error_message| anyhow!("Component error: {}", error_message))?;
-        router.post_return(&mut self.store)?;
-        let Some(rama_request) = self.store.data_mut().requests.remove(&resource_id) else {
-            anyhow::bail!("Couldn't find request ref cell with id {}", resource_id);
+        if pooled.is_vacant() {
+            let settings = instance.settings.lock().unwrap().clone();
+            pooled.fill(
+                self.instantiate_async(&instance.router_pre, &settings)
+                    .await?,
+            );
+        }
+        let (resource, fuel) = instance.begin_call(&self.config, &mut pooled, request)?;
+        let violations = pooled.store.data().limiter.violations;
+        let PooledRouter { store, router } = &mut *pooled;
+        let Router::Async(router) = router else {
+            unreachable!("checked above");
         };
-        Ok(rama_request)
+        // Host calls such as `body` are not interrupted by epochs, so the
+        // deadline also bounds the call as a whole.
+        let deadline = store.data().deadline;
+        let call = router
+            .wit_crossroads_router()
+            .call_handle(&mut *store, Resource::new_borrow(resource.rep()));
+        let call = match deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline.into(), call).await {
+                Ok(call) => call,
+                Err(_) => {
+                    instance.stats.lock().unwrap().traps += 1;
+                    return Err(HandleError::Timeout);
+                }
+            },
+            None => call.await,
+        }
+        .map(|result| result.map(Outcome::from).map_err(Error::from));
+        // Should this future be dropped before the call completes, the router
+        // stays dirty and is discarded as well.
+        instance.finish_call(&self.config, &mut pooled, resource, fuel, violations, call)
     }
 }
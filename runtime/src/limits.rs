@@ -0,0 +1,219 @@
+use std::fmt;
+
+use anyhow::Result;
+use rama::http::{HeaderMap, HeaderName, HeaderValue, Uri};
+use wasmtime::{
+    DEFAULT_INSTANCE_LIMIT, DEFAULT_MEMORY_LIMIT, DEFAULT_TABLE_LIMIT, ResourceLimiter,
+};
+
+use crate::Error;
+
+/// Limits on what a single router instance may allocate.
+#[derive(Debug, Clone, Default)]
+pub struct InstanceLimits {
+    /// Maximum size in bytes of each linear memory.
+    pub max_memory_size: Option<usize>,
+    /// Maximum number of elements of each table.
+    pub max_table_elements: Option<usize>,
+    /// Maximum number of core instances the component may create.
+    pub max_instances: Option<usize>,
+}
+
+/// Raised as a trap when a component grows beyond its [`InstanceLimits`].
+#[derive(Debug)]
+pub(crate) struct LimitExceeded(String);
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+/// Raised when a component exceeds one of the [`RequestQuotas`] in a host call
+/// that cannot return an error to it.
+#[derive(Debug)]
+pub(crate) struct QuotaExceeded(String);
+
+impl fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+impl From<QuotaExceeded> for Error {
+    fn from(error: QuotaExceeded) -> Self {
+        Error::QuotaExceeded(error.0)
+    }
+}
+
+/// Enforces [`InstanceLimits`] for a store and counts how often they were hit.
+pub(crate) struct Limiter {
+    pub(crate) limits: InstanceLimits,
+    pub(crate) violations: u64,
+}
+
+impl Limiter {
+    pub(crate) fn new(limits: InstanceLimits) -> Self {
+        Self {
+            limits,
+            violations: 0,
+        }
+    }
+}
+
+impl ResourceLimiter for Limiter {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> Result<bool> {
+        match self.limits.max_memory_size {
+            Some(max) if desired > max => {
+                self.violations += 1;
+                Err(LimitExceeded(format!(
+                    "Memory of {} bytes exceeds the limit of {} bytes",
+                    desired, max
+                ))
+                .into())
+            }
+            _ => Ok(true),
+        }
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> Result<bool> {
+        match self.limits.max_table_elements {
+            Some(max) if desired > max => {
+                self.violations += 1;
+                Err(LimitExceeded(format!(
+                    "Table of {} elements exceeds the limit of {} elements",
+                    desired, max
+                ))
+                .into())
+            }
+            _ => Ok(true),
+        }
+    }
+
+    fn instances(&self) -> usize {
+        self.limits.max_instances.unwrap_or(DEFAULT_INSTANCE_LIMIT)
+    }
+
+    fn tables(&self) -> usize {
+        DEFAULT_TABLE_LIMIT
+    }
+
+    fn memories(&self) -> usize {
+        DEFAULT_MEMORY_LIMIT
+    }
+}
+
+/// Quotas on how much a component may grow a single request, and the
+/// response and streams it creates while handling it.
+#[derive(Debug, Clone, Default)]
+pub struct RequestQuotas {
+    /// Maximum number of header values, of the request and of each response.
+    pub max_header_count: Option<usize>,
+    /// Maximum number of bytes of all header names and values combined, of the
+    /// request and of each response.
+    pub max_header_bytes: Option<usize>,
+    /// Maximum length of the serialized uri.
+    pub max_uri_length: Option<usize>,
+    /// Maximum size in bytes of a body set on the request or a response.
+    pub max_body_size: Option<usize>,
+    /// Maximum number of responses and body streams created in one call.
+    /// Creating a response beyond it traps, as the constructor cannot fail.
+    pub max_resources: Option<usize>,
+}
+
+impl RequestQuotas {
+    /// Checks that `headers` stay within the quotas once all values of
+    /// `replaced` are removed and `added` is inserted.
+    pub(crate) fn check_headers(
+        &self,
+        headers: &HeaderMap,
+        replaced: Option<&HeaderName>,
+        added: (&HeaderName, &HeaderValue),
+    ) -> Result<(), Error> {
+        let (replaced_count, replaced_bytes) = match replaced {
+            Some(name) => headers
+                .get_all(name)
+                .iter()
+                .fold((0, 0), |(count, bytes), value| {
+                    (count + 1, bytes + name.as_str().len() + value.len())
+                }),
+            None => (0, 0),
+        };
+        if let Some(max) = self.max_header_count {
+            let count = headers.len() - replaced_count + 1;
+            if count > max {
+                return Err(Error::QuotaExceeded(format!(
+                    "{} headers exceed the quota of {} headers",
+                    count, max
+                )));
+            }
+        }
+        if let Some(max) = self.max_header_bytes {
+            let current = headers
+                .iter()
+                .map(|(name, value)| name.as_str().len() + value.len())
+                .sum::<usize>();
+            let bytes = current - replaced_bytes + added.0.as_str().len() + added.1.len();
+            if bytes > max {
+                return Err(Error::QuotaExceeded(format!(
+                    "{} bytes of headers exceed the quota of {} bytes",
+                    bytes, max
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_body(&self, body: &[u8]) -> Result<(), Error> {
+        if let Some(max) = self.max_body_size
+            && body.len() > max
+        {
+            return Err(Error::QuotaExceeded(format!(
+                "Body of {} bytes exceeds the quota of {} bytes",
+                body.len(),
+                max
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks that one more resource may be created after `created` ones.
+    pub(crate) fn check_resources(&self, created: usize) -> Result<(), QuotaExceeded> {
+        if let Some(max) = self.max_resources
+            && created >= max
+        {
+            return Err(QuotaExceeded(format!(
+                "More than {} responses and body streams exceed the quota",
+                max
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_uri(&self, uri: &Uri) -> Result<(), Error> {
+        if let Some(max) = self.max_uri_length {
+            let length = uri.to_string().len();
+            if length > max {
+                return Err(Error::QuotaExceeded(format!(
+                    "Uri of {} bytes exceeds the quota of {} bytes",
+                    length, max
+                )));
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,26 +1,33 @@
-use anyhow::Result;
-use rama::http::{Body, Request};
+use std::path::{Path, PathBuf};
 
-use runtime::Runtime;
+use anyhow::{Context, Result};
+use rama::http::{Body, HeaderValue, Request};
+
+use runtime::{PoolingConfig, Runtime, RuntimeConfig, Version, WasiConfig};
 use tokio::time::{Duration, Instant};
 
 static PATH_TO_COMPONENT: &str = "../component/target/wasm32-wasip2/release/component.wasm";
 
-#[tokio::main]
-async fn main() -> Result<()> {
+/// Routes `runs` requests through a runtime created from `config` and returns
+/// the total time spent in `call_handle`.
+fn bench(config: RuntimeConfig, runs: usize) -> Result<Duration> {
     let body = Body::new::<String>("<H1>Hello !</H1>".into());
     let request = Request::builder()
         .method("GET")
         .uri("https://faa4abb5-c37a-4ba1-89df-a12075997594.functions.runs.onstackit.cloud/")
         .header("X-Custom-Foo", "Bar")
+        .header("X-Latin-1", HeaderValue::from_bytes(b"caf\xe9")?)
         .body(body)?;
 
-    let mut runtime = Runtime::new()?;
-    let id = runtime.add_instance(PATH_TO_COMPONENT)?;
+    let runtime = Runtime::new(config)?;
+    let id = runtime.add_instance(
+        "router",
+        Version::new(0, 1, 0),
+        PATH_TO_COMPONENT,
+        WasiConfig::default(),
+    )?;
 
-    let modified_request = runtime.call_handle(id, request)?;
-
-    let runs = 1000;
+    runtime.call_handle(id, request)?;
 
     let mut duration_handle = Duration::ZERO;
 
@@ -33,18 +40,108 @@ async fn main() -> Result<()> {
             .body(body)?;
 
         let start = Instant::now();
-        let modified_request = runtime.call_handle(id, request)?;
+        runtime.call_handle(id, request)?;
         let elapsed = start.elapsed();
         duration_handle += elapsed;
     }
 
+    Ok(duration_handle)
+}
+
+const USAGE: &str = "Usage: runtime [--fuel] [--epoch-tick-ms <ms>] [--pooling] \
+                     [compile <component.wasm> [<output.cwasm>]]";
+
+/// Builds the runtime config from the engine flags in `args`, and returns it
+/// with the remaining arguments. `compile` and the bench share it, so an
+/// artifact is precompiled for the very engine settings that load it.
+fn runtime_config(mut args: impl Iterator<Item = String>) -> Result<(RuntimeConfig, Vec<String>)> {
+    let mut config = RuntimeConfig::default();
+    let mut rest = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--fuel" => config.consume_fuel = true,
+            "--epoch-tick-ms" => {
+                let tick = args
+                    .next()
+                    .and_then(|tick| tick.parse().ok())
+                    .context(USAGE)?;
+                config.epoch_tick = Some(Duration::from_millis(tick));
+            }
+            "--pooling" => config.pooling = Some(PoolingConfig::default()),
+            flag if flag.starts_with("--") => anyhow::bail!("Unknown flag `{}`\n{}", flag, USAGE),
+            _ => rest.push(arg),
+        }
+    }
+    Ok((config, rest))
+}
+
+/// Writes `component.wasm`, precompiled for the engine settings of `config`,
+/// to `output` or next to the input with a `.cwasm` extension.
+fn compile(config: RuntimeConfig, args: &[String]) -> Result<()> {
+    let (input, output) = match args {
+        [input] => (input, Path::new(input).with_extension("cwasm")),
+        [input, output] => (input, PathBuf::from(output)),
+        _ => anyhow::bail!(USAGE),
+    };
+    let runtime = Runtime::new(config)?;
+    let artifact = runtime.precompile(input)?;
+    std::fs::write(&output, artifact)
+        .with_context(|| format!("Cannot write `{}`", output.display()))?;
+    println!("Wrote {}", output.display());
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let (config, args) = runtime_config(std::env::args().skip(1))?;
+    match args.split_first() {
+        Some((command, args)) if command == "compile" => return compile(config, args),
+        Some(_) => anyhow::bail!(USAGE),
+        None => {}
+    }
+
+    let runs = 1000;
+
+    let shared = bench(config.clone(), runs)?;
+    let fresh = bench(
+        RuntimeConfig {
+            isolate_calls: true,
+            ..config.clone()
+        },
+        runs,
+    )?;
+    let pooled = bench(
+        RuntimeConfig {
+            isolate_calls: true,
+            pooling: Some(PoolingConfig::default()),
+            ..config
+        },
+        runs,
+    )?;
+
+    for (mode, duration_handle) in [
+        ("shared", shared),
+        ("fresh", fresh),
+        ("pooled fresh", pooled),
+    ] {
+        println!(
+            "Total duration request ({} instance): {} seconds",
+            mode,
+            duration_handle.as_secs_f64()
+        );
+        println!(
+            "Average duration request ({} instance): {} seconds",
+            mode,
+            duration_handle.as_secs_f64() / runs as f64
+        );
+    }
     println!(
-        "Total duration request: {} seconds",
-        duration_handle.as_secs_f64()
+        "Average cost of a fresh instance: {} seconds",
+        (fresh.as_secs_f64() - shared.as_secs_f64()) / runs as f64
     );
     println!(
-        "Average duration request: {} seconds",
-        duration_handle.as_secs_f64() / runs as f64
+        "Instantiations per second with the pooling allocator: {}",
+        runs as f64 / pooled.as_secs_f64()
     );
 
     Ok(())
@@ -0,0 +1,179 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::Notify;
+use wasmtime::Store;
+
+use crate::{ComponentRunStates, HandleError, Router};
+
+/// A router instantiated in its own store. It handles one call at a time.
+pub(crate) struct PooledRouter {
+    pub(crate) store: Store<ComponentRunStates>,
+    pub(crate) router: Router,
+}
+
+/// The routers of one instance, shared between concurrent calls.
+pub(crate) struct Pool {
+    idle: Mutex<Idle>,
+    returned: Condvar,
+    returned_async: Notify,
+}
+
+/// The routers of a [`Pool`] that no call is using.
+struct Idle {
+    routers: Vec<PooledRouter>,
+    /// Number of routers that were discarded and have to be instantiated again
+    /// by the calls that take their place.
+    vacant: usize,
+}
+
+impl Idle {
+    fn take<'a>(&mut self, pool: &'a Pool) -> Option<Checkout<'a>> {
+        if let Some(router) = self.routers.pop() {
+            return Some(Checkout::new(pool, Some(router)));
+        }
+        if self.vacant > 0 {
+            self.vacant -= 1;
+            return Some(Checkout::new(pool, None));
+        }
+        None
+    }
+}
+
+impl Pool {
+    pub(crate) fn new(routers: Vec<PooledRouter>) -> Self {
+        Self {
+            idle: Mutex::new(Idle { routers, vacant: 0 }),
+            returned: Condvar::new(),
+            returned_async: Notify::new(),
+        }
+    }
+
+    /// Takes an idle router, blocking until one is returned if all are busy.
+    /// Fails with [`HandleError::PoolExhausted`] once `timeout` has passed.
+    /// The checkout may be [vacant](Checkout::is_vacant) instead.
+    pub(crate) fn checkout(&self, timeout: Option<Duration>) -> Result<Checkout<'_>, HandleError> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut idle = self.idle.lock().unwrap();
+        loop {
+            if let Some(checkout) = idle.take(self) {
+                return Ok(checkout);
+            }
+            idle = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Err(HandleError::PoolExhausted);
+                    }
+                    self.returned.wait_timeout(idle, remaining).unwrap().0
+                }
+                None => self.returned.wait(idle).unwrap(),
+            };
+        }
+    }
+
+    /// Async version of [`Pool::checkout`], which waits without blocking the
+    /// executor.
+    pub(crate) async fn checkout_async(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<Checkout<'_>, HandleError> {
+        let wait = async {
+            loop {
+                let returned = self.returned_async.notified();
+                let checkout = self.idle.lock().unwrap().take(self);
+                if let Some(checkout) = checkout {
+                    return checkout;
+                }
+                returned.await;
+            }
+        };
+        match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, wait)
+                .await
+                .map_err(|_| HandleError::PoolExhausted),
+            None => Ok(wait.await),
+        }
+    }
+}
+
+/// A router taken out of a [`Pool`], which is returned to it when dropped.
+///
+/// A checkout is dirty until [`Checkout::set_clean`] is called once the call
+/// on it completed. A dirty router, e.g. one that trapped or whose async call
+/// was dropped halfway, is discarded instead of returned, and the next call
+/// instantiates a fresh one in its place.
+pub(crate) struct Checkout<'a> {
+    pool: Option<&'a Pool>,
+    router: Option<PooledRouter>,
+    clean: bool,
+}
+
+impl<'a> Checkout<'a> {
+    fn new(pool: &'a Pool, router: Option<PooledRouter>) -> Self {
+        Self {
+            pool: Some(pool),
+            router,
+            clean: false,
+        }
+    }
+
+    /// Wraps a router that belongs to no pool and is dropped after the call.
+    pub(crate) fn fresh(router: PooledRouter) -> Self {
+        Self {
+            pool: None,
+            router: Some(router),
+            clean: false,
+        }
+    }
+
+    /// Whether the router of this checkout was discarded and has to be
+    /// [filled](Checkout::fill) before the call.
+    pub(crate) fn is_vacant(&self) -> bool {
+        self.router.is_none()
+    }
+
+    pub(crate) fn fill(&mut self, router: PooledRouter) {
+        self.router = Some(router);
+    }
+
+    /// Marks the call on the router as completed, so it can be reused.
+    pub(crate) fn set_clean(&mut self) {
+        self.clean = true;
+    }
+}
+
+impl Deref for Checkout<'_> {
+    type Target = PooledRouter;
+
+    fn deref(&self) -> &PooledRouter {
+        self.router
+            .as_ref()
+            .expect("vacant checkouts are filled first")
+    }
+}
+
+impl DerefMut for Checkout<'_> {
+    fn deref_mut(&mut self) -> &mut PooledRouter {
+        self.router
+            .as_mut()
+            .expect("vacant checkouts are filled first")
+    }
+}
+
+impl Drop for Checkout<'_> {
+    fn drop(&mut self) {
+        let Some(pool) = self.pool else {
+            return;
+        };
+        let mut idle = pool.idle.lock().unwrap();
+        match self.router.take() {
+            Some(router) if self.clean => idle.routers.push(router),
+            _ => idle.vacant += 1,
+        }
+        drop(idle);
+        pool.returned.notify_one();
+        pool.returned_async.notify_one();
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Result;
+use semver::Version;
+
+use crate::RouterInstance;
+
+/// A loaded component as listed by [`Runtime::components`](crate::Runtime::components).
+#[derive(Debug, Clone)]
+pub struct ComponentInfo {
+    /// The id calls to the component are made with.
+    pub id: usize,
+    pub name: String,
+    pub version: Version,
+    /// The file the component was loaded from.
+    pub path: Option<PathBuf>,
+    /// Hex encoded SHA-256 of the wasm or precompiled artifact.
+    pub hash: String,
+}
+
+/// The instances of a runtime, by id and by name and version.
+#[derive(Default)]
+pub(crate) struct Registry {
+    instances: HashMap<usize, Arc<RouterInstance>>,
+    versions: BTreeMap<String, BTreeMap<Version, usize>>,
+}
+
+impl Registry {
+    pub(crate) fn get(&self, id: usize) -> Option<&Arc<RouterInstance>> {
+        self.instances.get(&id)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (usize, &Arc<RouterInstance>)> {
+        self.instances.iter().map(|(id, instance)| (*id, instance))
+    }
+
+    /// Fails if the name and version of `instance` are already taken, or if
+    /// the name could not be referred to by [`Registry::resolve`].
+    pub(crate) fn insert(&mut self, id: usize, instance: RouterInstance) -> Result<()> {
+        if instance.name.is_empty() || instance.name.contains('@') {
+            anyhow::bail!(
+                "Component name `{}` must not be empty or contain `@`",
+                instance.name
+            );
+        }
+        let versions = self.versions.entry(instance.name.clone()).or_default();
+        if versions.contains_key(&instance.version) {
+            anyhow::bail!(
+                "Component {}@{} is already loaded",
+                instance.name,
+                instance.version
+            );
+        }
+        versions.insert(instance.version.clone(), id);
+        self.instances.insert(id, Arc::new(instance));
+        Ok(())
+    }
+
+    /// Replaces the instance `id` by `instance`, which keeps its name and version.
+    pub(crate) fn replace(&mut self, id: usize, instance: RouterInstance) -> Result<()> {
+        let Some(current) = self.instances.get_mut(&id) else {
+            anyhow::bail!("Couldn't find instance with id {}", id);
+        };
+        *current = Arc::new(instance);
+        Ok(())
+    }
+
+    pub(crate) fn remove(&mut self, id: usize) -> Option<Arc<RouterInstance>> {
+        let instance = self.instances.remove(&id)?;
+        if let Some(versions) = self.versions.get_mut(&instance.name) {
+            versions.remove(&instance.version);
+            if versions.is_empty() {
+                self.versions.remove(&instance.name);
+            }
+        }
+        Some(instance)
+    }
+
+    /// Looks up `name@version`, where the version may be `latest`. A bare
+    /// `name` also refers to the latest version.
+    pub(crate) fn resolve(&self, reference: &str) -> Result<usize> {
+        let (name, version) = reference.split_once('@').unwrap_or((reference, "latest"));
+        let Some(versions) = self.versions.get(name) else {
+            anyhow::bail!("No component named `{}` is loaded", name);
+        };
+        let id = if version == "latest" {
+            versions.values().next_back()
+        } else {
+            versions.get(&version.parse::<Version>()?)
+        };
+        match id {
+            Some(id) => Ok(*id),
+            None => anyhow::bail!("Component {}@{} is not loaded", name, version),
+        }
+    }
+}
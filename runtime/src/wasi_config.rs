@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use wasmtime_wasi::p2::{WasiCtx, WasiCtxBuilder};
+use wasmtime_wasi::{DirPerms, FilePerms};
+
+/// What a router sees of the host through WASI. The default grants nothing:
+/// no environment, no arguments, no files, no network and no stdio.
+#[derive(Debug, Clone, Default)]
+pub struct WasiConfig {
+    /// Names of host environment variables passed on to the router, if set.
+    pub env: Vec<String>,
+    /// Arguments the router sees, starting with the program name.
+    pub args: Vec<String>,
+    /// Host directories the router may read.
+    pub preopens: Vec<Preopen>,
+    pub stdio: Stdio,
+    /// Allow TCP and UDP sockets and name lookups.
+    pub network: bool,
+}
+
+/// A host directory opened read-only for a router.
+#[derive(Debug, Clone)]
+pub struct Preopen {
+    pub host_path: PathBuf,
+    /// The path under which the router sees the directory.
+    pub guest_path: String,
+}
+
+/// Where the stdio streams of a router lead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Stdio {
+    /// Stdin is empty and all output is discarded.
+    #[default]
+    Null,
+    /// Stdout and stderr are the host's, stdin is empty.
+    InheritOutput,
+    /// Stdin, stdout and stderr are the host's.
+    Inherit,
+}
+
+impl WasiConfig {
+    pub(crate) fn build(&self) -> Result<WasiCtx> {
+        let mut builder = WasiCtxBuilder::new();
+        for name in &self.env {
+            if let Ok(value) = std::env::var(name) {
+                builder.env(name, value);
+            }
+        }
+        builder.args(&self.args);
+        for preopen in &self.preopens {
+            builder
+                .preopened_dir(
+                    &preopen.host_path,
+                    &preopen.guest_path,
+                    DirPerms::READ,
+                    FilePerms::READ,
+                )
+                .with_context(|| {
+                    format!("Cannot open directory `{}`", preopen.host_path.display())
+                })?;
+        }
+        match self.stdio {
+            Stdio::Null => {}
+            Stdio::InheritOutput => {
+                builder.inherit_stdout().inherit_stderr();
+            }
+            Stdio::Inherit => {
+                builder.inherit_stdio();
+            }
+        }
+        if self.network {
+            builder.inherit_network().allow_ip_name_lookup(true);
+        } else {
+            builder
+                .allow_tcp(false)
+                .allow_udp(false)
+                .allow_ip_name_lookup(false);
+        }
+        Ok(builder.build())
+    }
+}
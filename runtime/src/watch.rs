@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use wasmtime_wasi::runtime::in_tokio;
+
+use crate::Runtime;
+
+/// The file an instance was loaded from.
+#[derive(Clone)]
+pub(crate) struct Source {
+    pub(crate) path: PathBuf,
+    /// Modification time of the file when it was loaded.
+    pub(crate) modified: Option<SystemTime>,
+    /// Whether the file is a precompiled artifact, which is not reloaded.
+    pub(crate) precompiled: bool,
+}
+
+impl Source {
+    /// Records the current modification time of `path`. Call this before
+    /// reading the file, so a change during the read is picked up later.
+    pub(crate) fn new(path: &Path, precompiled: bool) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            modified: modified(path),
+            precompiled,
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+impl Runtime {
+    /// Checks every `interval` whether the component file of an instance
+    /// added by [`Runtime::add_instance`] changed and replaces the instance by
+    /// the new version. If the new version fails to load, the old one keeps
+    /// running until the file changes again. Watching stops when the runtime
+    /// is dropped. A runtime is only watched once, so calling this again
+    /// fails.
+    pub fn watch(self: &Arc<Self>, interval: Duration) -> Result<()> {
+        if self.watching.swap(true, Ordering::Relaxed) {
+            anyhow::bail!("The runtime is already watched");
+        }
+        let runtime = Arc::downgrade(self);
+        std::thread::spawn(move || {
+            let mut failed = HashMap::new();
+            while let Some(runtime) = runtime.upgrade() {
+                runtime.reload_changed(&mut failed);
+                drop(runtime);
+                std::thread::sleep(interval);
+            }
+        });
+        Ok(())
+    }
+
+    /// Reloads every instance whose file changed since it was loaded, unless
+    /// that version already failed to load as recorded in `failed`.
+    fn reload_changed(&self, failed: &mut HashMap<usize, SystemTime>) {
+        let sources = self
+            .instances
+            .read()
+            .unwrap()
+            .iter()
+            .filter_map(|(id, instance)| {
+                let component = format!("{}@{}", instance.name, instance.version);
+                Some((id, component, instance.source.clone()?))
+            })
+            .filter(|(_, _, source)| !source.precompiled)
+            .collect::<Vec<_>>();
+        // Forget failures of instances that were removed or are no longer watched.
+        failed.retain(|failed_id, _| sources.iter().any(|(id, _, _)| id == failed_id));
+        for (id, component, source) in sources {
+            // The file may be missing while it is being rebuilt.
+            let Some(modified) = modified(&source.path) else {
+                continue;
+            };
+            if Some(modified) == source.modified || failed.get(&id) == Some(&modified) {
+                continue;
+            }
+            let path = source.path.display();
+            let reloaded = if self.config.async_support {
+                // The watcher has a thread of its own, which it may block.
+                in_tokio(self.replace_instance_async(id, &source.path))
+            } else {
+                self.replace_instance(id, &source.path)
+            };
+            match reloaded {
+                Ok(()) => {
+                    failed.remove(&id);
+                    tracing::info!(id, component, %path, "Reloaded component");
+                }
+                Err(error) => {
+                    failed.insert(id, modified);
+                    tracing::warn!(
+                        id,
+                        component,
+                        %path,
+                        error = format!("{:#}", error),
+                        "Cannot reload component, keeping the previous version"
+                    );
+                }
+            }
+        }
+    }
+}
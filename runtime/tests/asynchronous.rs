@@ -0,0 +1,138 @@
+mod common;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use bytes::Bytes;
+use rama::http::dep::http_body::Frame;
+use runtime::{HandleError, Routed, Runtime, RuntimeConfig, Version, WasiConfig};
+
+use common::{FORWARD, LOOP, READ_BODY, channel, forward, get, header, start, start_async};
+
+#[tokio::test]
+async fn body_waits_for_the_request_body_to_arrive() {
+    let (runtime, id) = start_async(RuntimeConfig::default(), READ_BODY).await;
+    let (sender, body) = channel();
+    let mut request = get("http://example.com/");
+    *request.body_mut() = body;
+    tokio::spawn(async move {
+        for chunk in ["hel", "lo"] {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let frame = Frame::data(Bytes::from_static(chunk.as_bytes()));
+            sender.send(Ok(frame)).await.unwrap();
+        }
+    });
+    let handled = runtime.call_handle_async(id, request).await.unwrap();
+    let Routed::Forward(request) = handled.routed else {
+        panic!("expected the request to be forwarded");
+    };
+    assert_eq!(header(&request, "x-body"), b"hello");
+}
+
+#[tokio::test]
+async fn body_stops_reading_at_max_size() {
+    let (runtime, id) = start_async(RuntimeConfig::default(), READ_BODY).await;
+    let (sender, body) = channel();
+    let mut request = get("http://example.com/");
+    *request.body_mut() = body;
+    // The sender stays open, so the body would never end.
+    let frame = Frame::data(Bytes::from_static(b"more than sixteen bytes"));
+    sender.send(Ok(frame)).await.unwrap();
+    let error = runtime.call_handle_async(id, request).await.err().unwrap();
+    assert!(matches!(error, HandleError::InvalidBody(_)), "{error}");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn running_router_yields_to_other_tasks() {
+    let config = RuntimeConfig {
+        epoch_tick: Some(Duration::from_millis(10)),
+        ..RuntimeConfig::default()
+    };
+    let (runtime, id) = start_async(config, LOOP).await;
+    runtime
+        .set_deadline(id, Some(Duration::from_millis(200)))
+        .unwrap();
+    let ran = Arc::new(AtomicBool::new(false));
+    let task = tokio::spawn({
+        let ran = ran.clone();
+        async move { ran.store(true, Ordering::SeqCst) }
+    });
+    let error = runtime
+        .call_handle_async(id, get("http://example.com/"))
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(error, HandleError::Timeout), "{error}");
+    // On a single threaded executor the task only ran if the router yielded.
+    assert!(ran.load(Ordering::SeqCst));
+    task.await.unwrap();
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn metered_router_yields_to_other_tasks() {
+    let config = RuntimeConfig {
+        consume_fuel: true,
+        ..RuntimeConfig::default()
+    };
+    let (runtime, id) = start_async(config, LOOP).await;
+    runtime.set_fuel_budget(id, Some(1_000_000)).unwrap();
+    let ran = Arc::new(AtomicBool::new(false));
+    let task = tokio::spawn({
+        let ran = ran.clone();
+        async move { ran.store(true, Ordering::SeqCst) }
+    });
+    let error = runtime
+        .call_handle_async(id, get("http://example.com/"))
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(error, HandleError::OutOfFuel), "{error}");
+    assert!(ran.load(Ordering::SeqCst));
+    task.await.unwrap();
+}
+
+#[tokio::test]
+async fn entry_point_of_the_other_mode_is_refused() {
+    let (runtime, id) = start_async(RuntimeConfig::default(), FORWARD).await;
+    let error = runtime
+        .call_handle(id, get("http://example.com/"))
+        .err()
+        .unwrap();
+    assert!(matches!(error, HandleError::WrongCallMode(_)), "{error}");
+
+    let (runtime, id) = start(RuntimeConfig::default(), FORWARD);
+    let error = runtime
+        .call_handle_async(id, get("http://example.com/"))
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(error, HandleError::WrongCallMode(_)), "{error}");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn loading_yields_to_other_tasks() {
+    let runtime = Runtime::new(RuntimeConfig {
+        async_support: true,
+        ..RuntimeConfig::default()
+    })
+    .unwrap();
+    let ran = Arc::new(AtomicBool::new(false));
+    let task = tokio::spawn({
+        let ran = ran.clone();
+        async move { ran.store(true, Ordering::SeqCst) }
+    });
+    runtime
+        .add_instance_from_bytes_async(
+            "router",
+            Version::new(0, 1, 0),
+            forward().as_bytes(),
+            WasiConfig::default(),
+        )
+        .await
+        .unwrap();
+    // On a single threaded executor the task only ran if compiling did not
+    // block it.
+    assert!(ran.load(Ordering::SeqCst));
+    task.await.unwrap();
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use std::convert::Infallible;
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::executor::block_on;
+use rama::http::dep::http_body::Frame;
+use rama::http::dep::http_body_util::{BodyExt, StreamBody};
+use rama::http::{Body, header};
+use runtime::{HandleError, RuntimeConfig};
+
+use common::{READ_BODY, READ_STREAM, forwarded, get, header, start};
+
+/// A body that never ends, made of `0123456789` frames.
+fn endless() -> Body {
+    Body::new(StreamBody::new(futures::stream::repeat_with(|| {
+        Ok::<_, Infallible>(Frame::data(Bytes::from_static(b"0123456789")))
+    })))
+}
+
+/// A body that delivers `data` after a short while.
+fn late(data: &'static str) -> Body {
+    Body::new(StreamBody::new(futures::stream::once(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        Ok::<_, Infallible>(Frame::data(Bytes::from_static(data.as_bytes())))
+    })))
+}
+
+#[test]
+fn body_is_read_and_still_forwarded() {
+    let (runtime, id) = start(RuntimeConfig::default(), READ_BODY);
+    let mut request = get("http://example.com/");
+    *request.body_mut() = Body::from("hello");
+    let request = forwarded(&runtime, id, request);
+    assert_eq!(header(&request, "x-body"), b"hello");
+    let body = block_on(request.into_body().collect()).unwrap().to_bytes();
+    assert_eq!(body, "hello");
+}
+
+#[test]
+fn body_larger_than_max_size_is_refused() {
+    let (runtime, id) = start(RuntimeConfig::default(), READ_BODY);
+    let mut request = get("http://example.com/");
+    *request.body_mut() = Body::from("this body is longer than sixteen bytes");
+    let error = runtime.call_handle(id, request).err().unwrap();
+    assert!(matches!(error, HandleError::InvalidBody(_)), "{error}");
+}
+
+#[test]
+fn endless_body_is_only_read_up_to_max_size() {
+    let (runtime, id) = start(RuntimeConfig::default(), READ_BODY);
+    let mut request = get("http://example.com/");
+    *request.body_mut() = endless();
+    let error = runtime.call_handle(id, request).err().unwrap();
+    assert!(matches!(error, HandleError::InvalidBody(_)), "{error}");
+}
+
+#[test]
+fn synchronous_body_waits_for_data() {
+    let (runtime, id) = start(RuntimeConfig::default(), READ_BODY);
+    let mut request = get("http://example.com/");
+    *request.body_mut() = late("hello");
+    let request = forwarded(&runtime, id, request);
+    assert_eq!(header(&request, "x-body"), b"hello");
+}
+
+#[test]
+fn body_stream_reads_as_the_body_arrives() {
+    let (runtime, id) = start(RuntimeConfig::default(), READ_STREAM);
+    let mut request = get("http://example.com/");
+    *request.body_mut() = endless();
+    let request = forwarded(&runtime, id, request);
+    assert_eq!(header(&request, "x-chunk"), b"0123");
+    // What the router read is consumed, the rest is forwarded.
+    let mut body = request.into_body();
+    let frame = block_on(body.frame()).unwrap().unwrap();
+    assert_eq!(frame.into_data().unwrap(), "456789");
+}
+
+#[test]
+fn streamed_body_drops_the_content_length() {
+    let (runtime, id) = start(RuntimeConfig::default(), READ_STREAM);
+    let mut request = get("http://example.com/");
+    request
+        .headers_mut()
+        .insert(header::CONTENT_LENGTH, 10.into());
+    *request.body_mut() = Body::from("0123456789");
+    let request = forwarded(&runtime, id, request);
+    assert!(!request.headers().contains_key(header::CONTENT_LENGTH));
+    let body = block_on(request.into_body().collect()).unwrap().to_bytes();
+    assert_eq!(body, "456789");
+}
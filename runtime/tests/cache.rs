@@ -0,0 +1,87 @@
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use runtime::{Runtime, RuntimeConfig, Version, WasiConfig};
+
+use common::{forward, get, scratch_dir};
+
+fn cached(cache_dir: &Path) -> RuntimeConfig {
+    RuntimeConfig {
+        cache_dir: Some(cache_dir.to_path_buf()),
+        ..RuntimeConfig::default()
+    }
+}
+
+/// Adds the forwarding router to a new runtime with `config` and calls it.
+fn add_and_call(config: RuntimeConfig) {
+    let runtime = Runtime::new(config).unwrap();
+    let id = runtime
+        .add_instance_from_bytes(
+            "router",
+            Version::new(0, 1, 0),
+            forward().as_bytes(),
+            WasiConfig::default(),
+        )
+        .unwrap();
+    runtime.call_handle(id, get("http://example.com/")).unwrap();
+}
+
+fn entries(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let mut entries = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|found| found == extension))
+        .collect::<Vec<_>>();
+    entries.sort();
+    entries
+}
+
+#[test]
+fn compiled_component_is_stored_once_and_reused() {
+    let dir = scratch_dir("cache-reuse");
+    add_and_call(cached(&dir));
+    let stored = entries(&dir, "cwasm");
+    assert_eq!(stored.len(), 1);
+    let written = fs::metadata(&stored[0]).unwrap().modified().unwrap();
+
+    // A second runtime finds the same entry and does not write it again.
+    add_and_call(cached(&dir));
+    assert_eq!(entries(&dir, "cwasm"), stored);
+    assert_eq!(
+        fs::metadata(&stored[0]).unwrap().modified().unwrap(),
+        written
+    );
+}
+
+#[test]
+fn corrupted_entry_is_compiled_again() {
+    let dir = scratch_dir("cache-corrupted");
+    add_and_call(cached(&dir));
+    let entry = entries(&dir, "cwasm").remove(0);
+    fs::write(&entry, b"not a component").unwrap();
+    add_and_call(cached(&dir));
+    assert_ne!(fs::read(&entry).unwrap(), b"not a component");
+}
+
+#[test]
+fn entries_of_other_engine_settings_are_kept() {
+    let dir = scratch_dir("cache-engines");
+    add_and_call(cached(&dir));
+    add_and_call(RuntimeConfig {
+        consume_fuel: true,
+        ..cached(&dir)
+    });
+    assert_eq!(entries(&dir, "cwasm").len(), 2);
+    assert_eq!(entries(&dir, "sha256").len(), 2);
+}
+
+#[test]
+fn unwritable_cache_does_not_fail_loading() {
+    let dir = scratch_dir("cache-unwritable");
+    // A file where the cache directory should be.
+    let cache_dir = dir.join("cache");
+    fs::write(&cache_dir, b"").unwrap();
+    add_and_call(cached(&cache_dir));
+}
@@ -0,0 +1,512 @@
+//! Routers for the integration tests, written as WebAssembly text so they need
+//! no guest toolchain.
+//!
+//! [`router`] wraps a core module fragment into a component that imports all
+//! of `wit:crossroads/types` and exports `wit:crossroads/router`. The fragment
+//! defines `$run`, which is called with the borrowed request and returns a
+//! pointer to the `result<outcome, error>` of the call. It can use:
+//!
+//! - every request function as `$method`, `$set_header`, `$body`, …, the
+//!   response functions as `$response_new`, `$response_set_status`, …, and
+//!   `$stream_read`/`$stream_drop` for `wasi:io/streams`, and
+//!   `$get_environment`, `$get_arguments` and `$get_directories` for what the
+//!   router sees of the host through WASI, all lowered with the canonical ABI,
+//! - `$continue`, `$respond`, `$reject`, `$internal` and `$fail` to build
+//!   the result, where `$fail` passes on the error returned by a host call,
+//! - memory below 4096 for data segments, and `{ret}` as the return area of
+//!   host calls, whose fields are at `{ret+4}`, `{ret+6}`, `{ret+8}` and
+//!   `{ret+12}`,
+//! - `{try}` after a host call to return its error, if it failed.
+#![allow(dead_code)]
+
+use std::convert::Infallible;
+use std::path::PathBuf;
+
+use bytes::Bytes;
+use rama::http::dep::http_body::Frame;
+use rama::http::dep::http_body_util::StreamBody;
+use rama::http::{Body, Request, Response};
+use runtime::{Routed, Runtime, RuntimeConfig, Version, WasiConfig};
+use tokio::sync::mpsc::Sender;
+
+/// Address of the return area that host calls in the fixtures write to.
+const RET: u32 = 4160;
+
+/// Wraps the core module fragment `core` into a router component.
+pub fn router(core: &str) -> String {
+    let core = core
+        .replace(
+            "{try}",
+            "(if (i32.load8_u (i32.const {ret})) (then (return (call $fail (i32.const {ret})))))",
+        )
+        .replace("{ret}", &RET.to_string())
+        .replace("{ret+4}", &(RET + 4).to_string())
+        .replace("{ret+6}", &(RET + 6).to_string())
+        .replace("{ret+8}", &(RET + 8).to_string())
+        .replace("{ret+12}", &(RET + 12).to_string());
+    PRELUDE.replace("{core}", &core)
+}
+
+/// Creates a runtime from `config` with the router made of `core` added as
+/// `router@0.1.0`.
+pub fn start(config: RuntimeConfig, core: &str) -> (Runtime, usize) {
+    let runtime = Runtime::new(config).unwrap();
+    let id = runtime
+        .add_instance_from_bytes(
+            "router",
+            Version::new(0, 1, 0),
+            router(core).as_bytes(),
+            WasiConfig::default(),
+        )
+        .unwrap();
+    (runtime, id)
+}
+
+/// Async version of [`start`], for a runtime with
+/// [`RuntimeConfig::async_support`].
+pub async fn start_async(config: RuntimeConfig, core: &str) -> (Runtime, usize) {
+    let runtime = Runtime::new(RuntimeConfig {
+        async_support: true,
+        ..config
+    })
+    .unwrap();
+    let id = runtime
+        .add_instance_from_bytes_async(
+            "router",
+            Version::new(0, 1, 0),
+            router(core).as_bytes(),
+            WasiConfig::default(),
+        )
+        .await
+        .unwrap();
+    (runtime, id)
+}
+
+/// Calls the router `id` and returns the request it forwarded.
+pub fn forwarded(runtime: &Runtime, id: usize, request: Request) -> Request {
+    match runtime.call_handle(id, request).unwrap().routed {
+        Routed::Forward(request) => request,
+        Routed::Respond(_) => panic!("expected the request to be forwarded"),
+    }
+}
+
+/// Calls the router `id` and returns the response it answered with.
+pub fn responded(runtime: &Runtime, id: usize, request: Request) -> Response {
+    match runtime.call_handle(id, request).unwrap().routed {
+        Routed::Respond(response) => response,
+        Routed::Forward(_) => panic!("expected the router to respond"),
+    }
+}
+
+/// The fragment of a router that forwards every request unchanged.
+pub const FORWARD: &str = r#"
+    (func $run (param $request i32) (result i32)
+      (call $continue))
+"#;
+
+/// A router that forwards every request unchanged.
+pub fn forward() -> String {
+    router(FORWARD)
+}
+
+/// Copies up to 16 bytes of the body into the `x-body` header. Waits for the
+/// body to arrive.
+pub const READ_BODY: &str = r#"
+    (data (i32.const 0) "x-body")
+    (func $run (param $request i32) (result i32)
+      (call $body (local.get $request) (i64.const 16) (i32.const {ret}))
+      {try}
+      (call $set_header (local.get $request) (i32.const 0) (i32.const 6)
+        (i32.load (i32.const {ret+4})) (i32.load (i32.const {ret+8})) (i32.const {ret}))
+      {try}
+      (call $continue))
+"#;
+
+/// Reads 4 bytes from the body stream into the `x-chunk` header.
+pub const READ_STREAM: &str = r#"
+    (data (i32.const 0) "x-chunk")
+    (func $run (param $request i32) (result i32)
+      (local $stream i32)
+      (call $body_stream (local.get $request) (i32.const {ret}))
+      {try}
+      (local.set $stream (i32.load (i32.const {ret+4})))
+      (call $stream_read (local.get $stream) (i64.const 4) (i32.const {ret}))
+      (if (i32.load8_u (i32.const {ret})) (then unreachable))
+      (call $set_header (local.get $request) (i32.const 0) (i32.const 7)
+        (i32.load (i32.const {ret+4})) (i32.load (i32.const {ret+8})) (i32.const {ret}))
+      {try}
+      (call $stream_drop (local.get $stream))
+      (call $continue))
+"#;
+
+/// Loops forever unless the request has an `x-stop` header.
+pub const LOOP: &str = r#"
+    (data (i32.const 0) "x-stop")
+    (func $run (param $request i32) (result i32)
+      (loop $forever
+        (call $get_header (local.get $request) (i32.const 0) (i32.const 6) (i32.const {ret}))
+        {try}
+        (br_if $forever (i32.eqz (i32.load (i32.const {ret+8})))))
+      (call $continue))
+"#;
+
+/// Waits for the whole body, then sets `x-router` to `{tag}`.
+const TAGGED: &str = r#"
+    (data (i32.const 0) "x-router")
+    (data (i32.const 16) "{tag}")
+    (func $run (param $request i32) (result i32)
+      (call $body (local.get $request) (i64.const 16) (i32.const {ret}))
+      {try}
+      (call $set_header (local.get $request)
+        (i32.const 0) (i32.const 8) (i32.const 16) (i32.const 1) (i32.const {ret}))
+      {try}
+      (call $continue))
+"#;
+
+/// A router that tags the requests it forwards with the single character
+/// `tag`, to tell versions of a component apart.
+pub fn tagged(tag: &str) -> String {
+    router(&TAGGED.replace("{tag}", tag))
+}
+
+/// Calls the router `id` and returns the tag it set.
+pub fn tag_of(runtime: &Runtime, id: usize) -> Vec<u8> {
+    header(
+        &forwarded(runtime, id, get("http://example.com/")),
+        "x-router",
+    )
+}
+
+/// A body whose frames arrive through the returned sender. It ends once the
+/// sender is dropped.
+pub fn channel() -> (Sender<Result<Frame<Bytes>, Infallible>>, Body) {
+    let (sender, receiver) = tokio::sync::mpsc::channel(4);
+    let stream = futures::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|frame| (frame, receiver))
+    });
+    (sender, Body::new(StreamBody::new(stream)))
+}
+
+/// A body that never delivers anything.
+pub fn stalled() -> Body {
+    Body::new(StreamBody::new(futures::stream::pending::<
+        Result<Frame<Bytes>, Infallible>,
+    >()))
+}
+
+/// Creates an empty directory for the test `name`, unique to this process.
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("crossroads-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Builds a `GET` request for `uri` with an empty body.
+pub fn get(uri: &str) -> Request {
+    Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(Body::empty())
+        .unwrap()
+}
+
+/// Returns the concatenated header values of `name`, as the routers in the
+/// tests report their findings through headers.
+pub fn header(request: &Request, name: &str) -> Vec<u8> {
+    request
+        .headers()
+        .get_all(name)
+        .iter()
+        .flat_map(|value| value.as_bytes().to_vec())
+        .collect()
+}
+
+const PRELUDE: &str = r#"
+(component $C
+  (import "wasi:io/error@0.2.3" (instance $io-error
+    (export "error" (type (sub resource)))
+  ))
+  (alias export $io-error "error" (type $io-error-t))
+  (import "wasi:io/streams@0.2.3" (instance $streams
+    (alias outer $C $io-error-t (type $error-t))
+    (export "error" (type $error (eq $error-t)))
+    (export "input-stream" (type $input-stream (sub resource)))
+    (type $stream-error-t (variant (case "last-operation-failed" (own $error)) (case "closed")))
+    (export "stream-error" (type $stream-error (eq $stream-error-t)))
+    (export "[method]input-stream.blocking-read"
+      (func (param "self" (borrow $input-stream)) (param "len" u64) (result (result (list u8) (error $stream-error)))))
+  ))
+  (alias export $streams "input-stream" (type $input-stream-t))
+  (import "wasi:cli/environment@0.2.3" (instance $environment
+    (export "get-environment" (func (result (list (tuple string string)))))
+    (export "get-arguments" (func (result (list string))))
+  ))
+  (import "wasi:filesystem/types@0.2.3" (instance $filesystem
+    (export "descriptor" (type (sub resource)))
+  ))
+  (alias export $filesystem "descriptor" (type $descriptor-t))
+  (import "wasi:filesystem/preopens@0.2.3" (instance $preopens
+    (alias outer $C $descriptor-t (type $descriptor-t))
+    (export "descriptor" (type $descriptor (eq $descriptor-t)))
+    (export "get-directories" (func (result (list (tuple (own $descriptor) string)))))
+  ))
+  (import "wit:crossroads/types@0.1.0" (instance $types
+    (alias outer $C $input-stream-t (type $input-stream-t))
+    (export "input-stream" (type $input-stream (eq $input-stream-t)))
+    (type $error-t (variant
+      (case "invalid-uri" string)
+      (case "invalid-method" string)
+      (case "invalid-header-name" string)
+      (case "invalid-header-value" string)
+      (case "invalid-status" string)
+      (case "invalid-body" string)
+      (case "not-found" string)
+      (case "rejected" u16)
+      (case "quota-exceeded" string)
+      (case "internal" string)))
+    (export "error" (type $error (eq $error-t)))
+    (export "request" (type $request (sub resource)))
+    (export "response" (type $response (sub resource)))
+    (type $outcome-t (variant (case "continue") (case "respond" (own $response))))
+    (export "outcome" (type $outcome (eq $outcome-t)))
+    (type $headers (list (tuple string (list u8))))
+    (export "[method]request.method" (func (param "self" (borrow $request)) (result (result string (error $error)))))
+    (export "[method]request.set-method" (func (param "self" (borrow $request)) (param "method" string) (result (result (error $error)))))
+    (export "[method]request.version" (func (param "self" (borrow $request)) (result (result string (error $error)))))
+    (export "[method]request.headers" (func (param "self" (borrow $request)) (result (result $headers (error $error)))))
+    (export "[method]request.set-header" (func (param "self" (borrow $request)) (param "key" string) (param "value" (list u8)) (result (result (error $error)))))
+    (export "[method]request.get-header" (func (param "self" (borrow $request)) (param "key" string) (result (result (list (list u8)) (error $error)))))
+    (export "[method]request.append-header" (func (param "self" (borrow $request)) (param "key" string) (param "value" (list u8)) (result (result (error $error)))))
+    (export "[method]request.remove-header" (func (param "self" (borrow $request)) (param "key" string) (result (result (error $error)))))
+    (export "[method]request.uri" (func (param "self" (borrow $request)) (result (result string (error $error)))))
+    (export "[method]request.set-uri" (func (param "self" (borrow $request)) (param "uri" string) (result (result (error $error)))))
+    (export "[method]request.scheme" (func (param "self" (borrow $request)) (result (result (option string) (error $error)))))
+    (export "[method]request.set-scheme" (func (param "self" (borrow $request)) (param "scheme" string) (result (result (error $error)))))
+    (export "[method]request.authority" (func (param "self" (borrow $request)) (result (result (option string) (error $error)))))
+    (export "[method]request.set-authority" (func (param "self" (borrow $request)) (param "authority" string) (result (result (error $error)))))
+    (export "[method]request.host" (func (param "self" (borrow $request)) (result (result (option string) (error $error)))))
+    (export "[method]request.set-host" (func (param "self" (borrow $request)) (param "host" string) (result (result (error $error)))))
+    (export "[method]request.port" (func (param "self" (borrow $request)) (result (result (option u16) (error $error)))))
+    (export "[method]request.set-port" (func (param "self" (borrow $request)) (param "port" (option u16)) (result (result (error $error)))))
+    (export "[method]request.path" (func (param "self" (borrow $request)) (result (result string (error $error)))))
+    (export "[method]request.set-path" (func (param "self" (borrow $request)) (param "path" string) (result (result (error $error)))))
+    (export "[method]request.query" (func (param "self" (borrow $request)) (result (result (option string) (error $error)))))
+    (export "[method]request.set-query" (func (param "self" (borrow $request)) (param "query" (option string)) (result (result (error $error)))))
+    (export "[method]request.body" (func (param "self" (borrow $request)) (param "max-size" u64) (result (result (list u8) (error $error)))))
+    (export "[method]request.body-stream" (func (param "self" (borrow $request)) (result (result (own $input-stream) (error $error)))))
+    (export "[method]request.set-body" (func (param "self" (borrow $request)) (param "body" (list u8)) (result (result (error $error)))))
+    (export "[constructor]response" (func (result (own $response))))
+    (export "[method]response.status" (func (param "self" (borrow $response)) (result (result u16 (error $error)))))
+    (export "[method]response.set-status" (func (param "self" (borrow $response)) (param "status" u16) (result (result (error $error)))))
+    (export "[method]response.headers" (func (param "self" (borrow $response)) (result (result $headers (error $error)))))
+    (export "[method]response.set-header" (func (param "self" (borrow $response)) (param "key" string) (param "value" (list u8)) (result (result (error $error)))))
+    (export "[method]response.set-body" (func (param "self" (borrow $response)) (param "body" (list u8)) (result (result (error $error)))))
+  ))
+  (alias export $types "request" (type $request))
+  (alias export $types "response" (type $response))
+  (alias export $types "error" (type $error))
+  (alias export $types "outcome" (type $outcome))
+
+  (core module $Libc
+    (memory (export "memory") 1)
+    (global $bump (export "bump") (mut i32) (i32.const 8192))
+    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+      (local $ptr i32)
+      (local.set $ptr
+        (i32.and
+          (i32.add (global.get $bump) (i32.sub (local.get 2) (i32.const 1)))
+          (i32.sub (i32.const 0) (local.get 2))))
+      (global.set $bump (i32.add (local.get $ptr) (local.get 3)))
+      (if (i32.gt_u (global.get $bump) (i32.mul (memory.size) (i32.const 65536)))
+        (then
+          (if (i32.eq
+                (memory.grow
+                  (i32.sub
+                    (i32.add (i32.shr_u (global.get $bump) (i32.const 16)) (i32.const 1))
+                    (memory.size)))
+                (i32.const -1))
+            (then unreachable))))
+      (local.get $ptr))
+  )
+  (core instance $libc (instantiate $Libc))
+  (alias core export $libc "memory" (core memory $memory))
+  (alias core export $libc "realloc" (core func $realloc))
+
+  (core func $method (canon lower (func $types "[method]request.method") (memory $memory) (realloc $realloc)))
+  (core func $set_method (canon lower (func $types "[method]request.set-method") (memory $memory) (realloc $realloc)))
+  (core func $version (canon lower (func $types "[method]request.version") (memory $memory) (realloc $realloc)))
+  (core func $headers (canon lower (func $types "[method]request.headers") (memory $memory) (realloc $realloc)))
+  (core func $set_header (canon lower (func $types "[method]request.set-header") (memory $memory) (realloc $realloc)))
+  (core func $get_header (canon lower (func $types "[method]request.get-header") (memory $memory) (realloc $realloc)))
+  (core func $append_header (canon lower (func $types "[method]request.append-header") (memory $memory) (realloc $realloc)))
+  (core func $remove_header (canon lower (func $types "[method]request.remove-header") (memory $memory) (realloc $realloc)))
+  (core func $uri (canon lower (func $types "[method]request.uri") (memory $memory) (realloc $realloc)))
+  (core func $set_uri (canon lower (func $types "[method]request.set-uri") (memory $memory) (realloc $realloc)))
+  (core func $scheme (canon lower (func $types "[method]request.scheme") (memory $memory) (realloc $realloc)))
+  (core func $set_scheme (canon lower (func $types "[method]request.set-scheme") (memory $memory) (realloc $realloc)))
+  (core func $authority (canon lower (func $types "[method]request.authority") (memory $memory) (realloc $realloc)))
+  (core func $set_authority (canon lower (func $types "[method]request.set-authority") (memory $memory) (realloc $realloc)))
+  (core func $host (canon lower (func $types "[method]request.host") (memory $memory) (realloc $realloc)))
+  (core func $set_host (canon lower (func $types "[method]request.set-host") (memory $memory) (realloc $realloc)))
+  (core func $port (canon lower (func $types "[method]request.port") (memory $memory) (realloc $realloc)))
+  (core func $set_port (canon lower (func $types "[method]request.set-port") (memory $memory) (realloc $realloc)))
+  (core func $path (canon lower (func $types "[method]request.path") (memory $memory) (realloc $realloc)))
+  (core func $set_path (canon lower (func $types "[method]request.set-path") (memory $memory) (realloc $realloc)))
+  (core func $query (canon lower (func $types "[method]request.query") (memory $memory) (realloc $realloc)))
+  (core func $set_query (canon lower (func $types "[method]request.set-query") (memory $memory) (realloc $realloc)))
+  (core func $body (canon lower (func $types "[method]request.body") (memory $memory) (realloc $realloc)))
+  (core func $body_stream (canon lower (func $types "[method]request.body-stream") (memory $memory) (realloc $realloc)))
+  (core func $set_body (canon lower (func $types "[method]request.set-body") (memory $memory) (realloc $realloc)))
+  (core func $response_new (canon lower (func $types "[constructor]response")))
+  (core func $response_status (canon lower (func $types "[method]response.status") (memory $memory) (realloc $realloc)))
+  (core func $response_set_status (canon lower (func $types "[method]response.set-status") (memory $memory) (realloc $realloc)))
+  (core func $response_headers (canon lower (func $types "[method]response.headers") (memory $memory) (realloc $realloc)))
+  (core func $response_set_header (canon lower (func $types "[method]response.set-header") (memory $memory) (realloc $realloc)))
+  (core func $response_set_body (canon lower (func $types "[method]response.set-body") (memory $memory) (realloc $realloc)))
+  (core func $request_drop (canon resource.drop $request))
+  (core func $response_drop (canon resource.drop $response))
+  (core func $stream_read (canon lower (func $streams "[method]input-stream.blocking-read") (memory $memory) (realloc $realloc)))
+  (core func $stream_drop (canon resource.drop $input-stream-t))
+  (core func $get_environment (canon lower (func $environment "get-environment") (memory $memory) (realloc $realloc)))
+  (core func $get_arguments (canon lower (func $environment "get-arguments") (memory $memory) (realloc $realloc)))
+  (core func $get_directories (canon lower (func $preopens "get-directories") (memory $memory) (realloc $realloc)))
+
+  (core instance $host
+    (export "method" (func $method))
+    (export "set_method" (func $set_method))
+    (export "version" (func $version))
+    (export "headers" (func $headers))
+    (export "set_header" (func $set_header))
+    (export "get_header" (func $get_header))
+    (export "append_header" (func $append_header))
+    (export "remove_header" (func $remove_header))
+    (export "uri" (func $uri))
+    (export "set_uri" (func $set_uri))
+    (export "scheme" (func $scheme))
+    (export "set_scheme" (func $set_scheme))
+    (export "authority" (func $authority))
+    (export "set_authority" (func $set_authority))
+    (export "host" (func $host))
+    (export "set_host" (func $set_host))
+    (export "port" (func $port))
+    (export "set_port" (func $set_port))
+    (export "path" (func $path))
+    (export "set_path" (func $set_path))
+    (export "query" (func $query))
+    (export "set_query" (func $set_query))
+    (export "body" (func $body))
+    (export "body_stream" (func $body_stream))
+    (export "set_body" (func $set_body))
+    (export "response_new" (func $response_new))
+    (export "response_status" (func $response_status))
+    (export "response_set_status" (func $response_set_status))
+    (export "response_headers" (func $response_headers))
+    (export "response_set_header" (func $response_set_header))
+    (export "response_set_body" (func $response_set_body))
+    (export "request_drop" (func $request_drop))
+    (export "response_drop" (func $response_drop))
+    (export "stream_read" (func $stream_read))
+    (export "stream_drop" (func $stream_drop))
+    (export "get_environment" (func $get_environment))
+    (export "get_arguments" (func $get_arguments))
+    (export "get_directories" (func $get_directories))
+  )
+
+  (core module $Router
+    (import "libc" "memory" (memory 1))
+    (import "libc" "bump" (global $bump (mut i32)))
+    (import "host" "method" (func $method (param i32 i32)))
+    (import "host" "set_method" (func $set_method (param i32 i32 i32 i32)))
+    (import "host" "version" (func $version (param i32 i32)))
+    (import "host" "headers" (func $headers (param i32 i32)))
+    (import "host" "set_header" (func $set_header (param i32 i32 i32 i32 i32 i32)))
+    (import "host" "get_header" (func $get_header (param i32 i32 i32 i32)))
+    (import "host" "append_header" (func $append_header (param i32 i32 i32 i32 i32 i32)))
+    (import "host" "remove_header" (func $remove_header (param i32 i32 i32 i32)))
+    (import "host" "uri" (func $uri (param i32 i32)))
+    (import "host" "set_uri" (func $set_uri (param i32 i32 i32 i32)))
+    (import "host" "scheme" (func $scheme (param i32 i32)))
+    (import "host" "set_scheme" (func $set_scheme (param i32 i32 i32 i32)))
+    (import "host" "authority" (func $authority (param i32 i32)))
+    (import "host" "set_authority" (func $set_authority (param i32 i32 i32 i32)))
+    (import "host" "host" (func $host (param i32 i32)))
+    (import "host" "set_host" (func $set_host (param i32 i32 i32 i32)))
+    (import "host" "port" (func $port (param i32 i32)))
+    (import "host" "set_port" (func $set_port (param i32 i32 i32 i32)))
+    (import "host" "path" (func $path (param i32 i32)))
+    (import "host" "set_path" (func $set_path (param i32 i32 i32 i32)))
+    (import "host" "query" (func $query (param i32 i32)))
+    (import "host" "set_query" (func $set_query (param i32 i32 i32 i32 i32)))
+    (import "host" "body" (func $body (param i32 i64 i32)))
+    (import "host" "body_stream" (func $body_stream (param i32 i32)))
+    (import "host" "set_body" (func $set_body (param i32 i32 i32 i32)))
+    (import "host" "response_new" (func $response_new (result i32)))
+    (import "host" "response_status" (func $response_status (param i32 i32)))
+    (import "host" "response_set_status" (func $response_set_status (param i32 i32 i32)))
+    (import "host" "response_headers" (func $response_headers (param i32 i32)))
+    (import "host" "response_set_header" (func $response_set_header (param i32 i32 i32 i32 i32 i32)))
+    (import "host" "response_set_body" (func $response_set_body (param i32 i32 i32 i32)))
+    (import "host" "request_drop" (func $request_drop (param i32)))
+    (import "host" "response_drop" (func $response_drop (param i32)))
+    (import "host" "stream_read" (func $stream_read (param i32 i64 i32)))
+    (import "host" "stream_drop" (func $stream_drop (param i32)))
+    (import "host" "get_environment" (func $get_environment (param i32)))
+    (import "host" "get_arguments" (func $get_arguments (param i32)))
+    (import "host" "get_directories" (func $get_directories (param i32)))
+
+    ;; The borrowed request has to be dropped before `handle` returns.
+    (func (export "handle") (param $request i32) (result i32)
+      (local $result i32)
+      (global.set $bump (i32.const 8192))
+      (local.set $result (call $run (local.get $request)))
+      (call $request_drop (local.get $request))
+      (local.get $result))
+
+    ;; The result of `handle` is written to 4096.
+    (func $continue (result i32)
+      (i32.store8 (i32.const 4096) (i32.const 0))
+      (i32.store8 (i32.const 4100) (i32.const 0))
+      (i32.const 4096))
+    (func $respond (param $response i32) (result i32)
+      (i32.store8 (i32.const 4096) (i32.const 0))
+      (i32.store8 (i32.const 4100) (i32.const 1))
+      (i32.store (i32.const 4104) (local.get $response))
+      (i32.const 4096))
+    (func $reject (param $status i32) (result i32)
+      (i32.store8 (i32.const 4096) (i32.const 1))
+      (i32.store8 (i32.const 4100) (i32.const 7))
+      (i32.store16 (i32.const 4104) (local.get $status))
+      (i32.const 4096))
+    (func $internal (param $message i32) (param $length i32) (result i32)
+      (i32.store8 (i32.const 4096) (i32.const 1))
+      (i32.store8 (i32.const 4100) (i32.const 9))
+      (i32.store (i32.const 4104) (local.get $message))
+      (i32.store (i32.const 4108) (local.get $length))
+      (i32.const 4096))
+    ;; Returns the error of the host call whose result is at `$result`.
+    (func $fail (param $result i32) (result i32)
+      (i32.store8 (i32.const 4096) (i32.const 1))
+      (i32.store (i32.const 4100) (i32.load (i32.add (local.get $result) (i32.const 4))))
+      (i32.store (i32.const 4104) (i32.load (i32.add (local.get $result) (i32.const 8))))
+      (i32.store (i32.const 4108) (i32.load (i32.add (local.get $result) (i32.const 12))))
+      (i32.const 4096))
+
+    {core}
+  )
+  (core instance $router (instantiate $Router
+    (with "libc" (instance $libc))
+    (with "host" (instance $host))
+  ))
+
+  (func $handle (param "request" (borrow $request)) (result (result $outcome (error $error)))
+    (canon lift (core func $router "handle") (memory $memory) (realloc $realloc)))
+  (instance $exports
+    (export "request" (type $request))
+    (export "outcome" (type $outcome))
+    (export "error" (type $error))
+    (export "handle" (func $handle))
+  )
+  (export "wit:crossroads/router@0.1.0" (instance $exports))
+)
+"#;
@@ -0,0 +1,70 @@
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use runtime::{Runtime, RuntimeConfig, Version, WasiConfig};
+
+use common::{forward, get, scratch_dir};
+
+/// Runs `runtime <flags> compile` on the forwarding router and returns the
+/// artifact it wrote.
+fn compile(dir: &Path, flags: &[&str]) -> PathBuf {
+    let input = dir.join("router.wasm");
+    let artifact = dir.join("router.cwasm");
+    std::fs::write(&input, forward()).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_runtime"))
+        .args(flags)
+        .arg("compile")
+        .args([&input, &artifact])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    artifact
+}
+
+fn add_precompiled(config: RuntimeConfig, artifact: &Path) -> anyhow::Result<(Runtime, usize)> {
+    let runtime = Runtime::new(config)?;
+    // SAFETY: the artifact was just written by `runtime compile`.
+    let id = unsafe {
+        runtime.add_precompiled(
+            "router",
+            Version::new(0, 1, 0),
+            artifact,
+            WasiConfig::default(),
+        )?
+    };
+    Ok((runtime, id))
+}
+
+#[test]
+fn compile_uses_the_engine_flags() {
+    let dir = scratch_dir("compile-flags");
+    let artifact = compile(&dir, &["--fuel", "--epoch-tick-ms", "10"]);
+    let config = RuntimeConfig {
+        consume_fuel: true,
+        epoch_tick: Some(std::time::Duration::from_millis(10)),
+        ..RuntimeConfig::default()
+    };
+    let (runtime, id) = add_precompiled(config, &artifact).unwrap();
+    runtime.call_handle(id, get("http://example.com/")).unwrap();
+}
+
+#[test]
+fn artifact_of_other_engine_settings_is_refused() {
+    let dir = scratch_dir("compile-mismatch");
+    let artifact = compile(&dir, &["--fuel"]);
+    let error = add_precompiled(RuntimeConfig::default(), &artifact)
+        .err()
+        .unwrap();
+    assert!(format!("{:#}", error).contains("Cannot use"), "{error:#}");
+}
+
+#[test]
+fn unknown_flag_is_refused() {
+    let output = Command::new(env!("CARGO_BIN_EXE_runtime"))
+        .args(["--fast", "compile", "router.wasm"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
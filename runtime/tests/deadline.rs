@@ -0,0 +1,93 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use rama::http::{Body, Request};
+use runtime::{HandleError, Handled, Runtime, RuntimeConfig};
+
+use common::{FORWARD, LOOP, READ_BODY, READ_STREAM, get, stalled, start, start_async};
+
+/// A request whose body never arrives.
+fn stalled_request() -> Request {
+    let mut request = get("http://example.com/");
+    *request.body_mut() = stalled();
+    request
+}
+
+/// Checks that a call started at `started` timed out at its deadline of 50ms.
+fn assert_timed_out(started: Instant, result: Result<Handled, HandleError>) {
+    let error = result.err().unwrap();
+    assert!(matches!(error, HandleError::Timeout), "{error}");
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_millis(50), "{elapsed:?}");
+    assert!(elapsed < Duration::from_secs(5), "{elapsed:?}");
+}
+
+fn ticking() -> RuntimeConfig {
+    RuntimeConfig {
+        epoch_tick: Some(Duration::from_millis(10)),
+        ..RuntimeConfig::default()
+    }
+}
+
+#[test]
+fn endless_loop_is_interrupted_at_its_deadline() {
+    let (runtime, id) = start(ticking(), LOOP);
+    runtime
+        .set_deadline(id, Some(Duration::from_millis(50)))
+        .unwrap();
+    let started = Instant::now();
+    assert_timed_out(started, runtime.call_handle(id, get("http://example.com/")));
+}
+
+#[test]
+fn deadline_below_one_tick_still_lets_short_calls_finish() {
+    let (runtime, id) = start(ticking(), FORWARD);
+    runtime
+        .set_deadline(id, Some(Duration::from_nanos(1)))
+        .unwrap();
+    runtime.call_handle(id, get("http://example.com/")).unwrap();
+}
+
+#[test]
+fn zero_epoch_tick_is_refused() {
+    let config = RuntimeConfig {
+        epoch_tick: Some(Duration::ZERO),
+        ..RuntimeConfig::default()
+    };
+    assert!(Runtime::new(config).is_err());
+}
+
+#[test]
+fn stalled_body_is_cut_off_at_the_deadline() {
+    for core in [READ_BODY, READ_STREAM] {
+        let (runtime, id) = start(ticking(), core);
+        runtime
+            .set_deadline(id, Some(Duration::from_millis(50)))
+            .unwrap();
+        let started = Instant::now();
+        assert_timed_out(started, runtime.call_handle(id, stalled_request()));
+        assert_eq!(runtime.stats(id).unwrap().traps, 1);
+    }
+}
+
+#[tokio::test]
+async fn stalled_body_is_cut_off_at_the_deadline_in_async_calls() {
+    for core in [READ_BODY, READ_STREAM] {
+        let (runtime, id) = start_async(ticking(), core).await;
+        runtime
+            .set_deadline(id, Some(Duration::from_millis(50)))
+            .unwrap();
+        let started = Instant::now();
+        assert_timed_out(
+            started,
+            runtime.call_handle_async(id, stalled_request()).await,
+        );
+        assert_eq!(runtime.stats(id).unwrap().traps, 1);
+        // The router that was cut off is replaced.
+        runtime.set_deadline(id, None).unwrap();
+        let mut request = get("http://example.com/");
+        *request.body_mut() = Body::from("done");
+        runtime.call_handle_async(id, request).await.unwrap();
+    }
+}
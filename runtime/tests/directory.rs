@@ -0,0 +1,93 @@
+mod common;
+
+use std::path::Path;
+
+use runtime::{Runtime, RuntimeConfig, WasiConfig};
+
+use common::{forward, get, scratch_dir};
+
+fn write(dir: &Path, file: &str, contents: &str) {
+    std::fs::write(dir.join(file), contents).unwrap();
+}
+
+#[test]
+fn directory_is_loaded_with_an_outcome_per_file() {
+    let dir = scratch_dir("directory-outcomes");
+    write(&dir, "router@0.1.0.wasm", &forward());
+    write(&dir, "router@0.2.0.wasm", &forward());
+    write(&dir, "broken@1.0.0.wasm", "(component");
+    write(&dir, "router@next.wasm", &forward());
+    write(&dir, "router.wasm", &forward());
+    write(&dir, "README.md", "not a component");
+    let runtime = Runtime::new(RuntimeConfig::default()).unwrap();
+
+    let outcomes = runtime
+        .add_instances_from_dir(&dir, WasiConfig::default())
+        .unwrap();
+    let files = outcomes
+        .iter()
+        .map(|(path, outcome)| {
+            let file = path.file_name().unwrap().to_str().unwrap();
+            (file, outcome.is_ok())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        files,
+        [
+            ("broken@1.0.0.wasm", false),
+            ("router.wasm", false),
+            ("router@0.1.0.wasm", true),
+            ("router@0.2.0.wasm", true),
+            ("router@next.wasm", false),
+        ]
+    );
+
+    let id = runtime.resolve("router").unwrap();
+    assert_eq!(*outcomes[3].1.as_ref().unwrap(), id);
+    runtime.call_handle(id, get("http://example.com/")).unwrap();
+    assert_eq!(runtime.components().len(), 2);
+}
+
+#[test]
+fn file_without_a_version_is_refused() {
+    let dir = scratch_dir("directory-unversioned");
+    write(&dir, "router.wasm", &forward());
+    let runtime = Runtime::new(RuntimeConfig::default()).unwrap();
+    let mut outcomes = runtime
+        .add_instances_from_dir(&dir, WasiConfig::default())
+        .unwrap();
+    let error = outcomes.remove(0).1.err().unwrap();
+    assert!(error.to_string().contains("name@version.wasm"), "{error:#}");
+    assert!(runtime.components().is_empty());
+}
+
+#[test]
+fn missing_directory_fails_as_a_whole() {
+    let dir = scratch_dir("directory-missing").join("missing");
+    let runtime = Runtime::new(RuntimeConfig::default()).unwrap();
+    assert!(
+        runtime
+            .add_instances_from_dir(&dir, WasiConfig::default())
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn directory_is_loaded_asynchronously() {
+    let dir = scratch_dir("directory-async");
+    write(&dir, "router@0.1.0.wasm", &forward());
+    let runtime = Runtime::new(RuntimeConfig {
+        async_support: true,
+        ..RuntimeConfig::default()
+    })
+    .unwrap();
+    let outcomes = runtime
+        .add_instances_from_dir_async(&dir, WasiConfig::default())
+        .await
+        .unwrap();
+    let id = *outcomes[0].1.as_ref().unwrap();
+    runtime
+        .call_handle_async(id, get("http://example.com/"))
+        .await
+        .unwrap();
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use rama::http::StatusCode;
+use runtime::{HandleError, RuntimeConfig};
+
+use common::{get, start};
+
+const REJECT: &str = r#"
+    (func $run (param $request i32) (result i32)
+      (call $reject (i32.const 404)))
+"#;
+
+const REJECT_INVALID_STATUS: &str = r#"
+    (func $run (param $request i32) (result i32)
+      (call $reject (i32.const 42)))
+"#;
+
+const FAIL_INTERNALLY: &str = r#"
+    (data (i32.const 0) "no upstream configured")
+    (func $run (param $request i32) (result i32)
+      (call $internal (i32.const 0) (i32.const 22)))
+"#;
+
+/// Sets a uri that does not parse.
+const INVALID_URI: &str = r#"
+    (data (i32.const 0) "http://exa mple.com/")
+    (func $run (param $request i32) (result i32)
+      (call $set_uri (local.get $request) (i32.const 0) (i32.const 20) (i32.const {ret}))
+      {try}
+      (call $continue))
+"#;
+
+fn error_of(core: &str) -> HandleError {
+    let (runtime, id) = start(RuntimeConfig::default(), core);
+    runtime
+        .call_handle(id, get("http://example.com/"))
+        .err()
+        .unwrap()
+}
+
+#[test]
+fn rejection_carries_the_status() {
+    let error = error_of(REJECT);
+    assert!(
+        matches!(error, HandleError::Rejected(StatusCode::NOT_FOUND)),
+        "{error}"
+    );
+}
+
+#[test]
+fn rejection_with_invalid_status_is_an_invalid_status() {
+    let error = error_of(REJECT_INVALID_STATUS);
+    assert!(matches!(error, HandleError::InvalidStatus(_)), "{error}");
+}
+
+#[test]
+fn internal_error_carries_the_message() {
+    match error_of(FAIL_INTERNALLY) {
+        HandleError::Internal(message) => assert_eq!(message, "no upstream configured"),
+        error => panic!("unexpected error: {error}"),
+    }
+}
+
+#[test]
+fn host_errors_are_passed_on_typed() {
+    let error = error_of(INVALID_URI);
+    assert!(matches!(error, HandleError::InvalidUri(_)), "{error}");
+}
+
+#[test]
+fn unknown_instance_is_reported() {
+    let (runtime, id) = start(RuntimeConfig::default(), REJECT);
+    let error = runtime
+        .call_handle(id + 1, get("http://example.com/"))
+        .err()
+        .unwrap();
+    assert!(matches!(error, HandleError::UnknownInstance(_)), "{error}");
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use runtime::{HandleError, RuntimeConfig};
+
+use common::{FORWARD, LOOP, get, start};
+
+const REJECT: &str = r#"
+    (func $run (param $request i32) (result i32)
+      (call $reject (i32.const 403)))
+"#;
+
+fn metered() -> RuntimeConfig {
+    RuntimeConfig {
+        consume_fuel: true,
+        ..RuntimeConfig::default()
+    }
+}
+
+#[test]
+fn fuel_consumed_is_reported() {
+    let (runtime, id) = start(metered(), FORWARD);
+    runtime.set_fuel_budget(id, Some(100_000)).unwrap();
+    let handled = runtime.call_handle(id, get("http://example.com/")).unwrap();
+    let fuel_consumed = handled.fuel_consumed.unwrap();
+    assert!(
+        fuel_consumed > 0 && fuel_consumed <= 100_000,
+        "{fuel_consumed}"
+    );
+}
+
+#[test]
+fn endless_loop_runs_out_of_fuel() {
+    let (runtime, id) = start(metered(), LOOP);
+    runtime.set_fuel_budget(id, Some(100_000)).unwrap();
+    let error = runtime
+        .call_handle(id, get("http://example.com/"))
+        .err()
+        .unwrap();
+    assert!(matches!(error, HandleError::OutOfFuel), "{error}");
+    assert_eq!(runtime.stats(id).unwrap().fuel_consumed, 100_000);
+
+    // The router that ran dry was replaced and gets a full budget again.
+    let mut request = get("http://example.com/");
+    request.headers_mut().insert("x-stop", "1".parse().unwrap());
+    let handled = runtime.call_handle(id, request).unwrap();
+    assert_eq!(
+        runtime.stats(id).unwrap().fuel_consumed,
+        100_000 + handled.fuel_consumed.unwrap()
+    );
+}
+
+#[test]
+fn fuel_of_rejected_calls_is_counted_in_the_stats() {
+    let (runtime, id) = start(metered(), REJECT);
+    let error = runtime
+        .call_handle(id, get("http://example.com/"))
+        .err()
+        .unwrap();
+    assert!(matches!(error, HandleError::Rejected(_)), "{error}");
+    assert!(runtime.stats(id).unwrap().fuel_consumed > 0);
+}
+
+#[test]
+fn fuel_budget_needs_fuel_consumption() {
+    let (runtime, id) = start(RuntimeConfig::default(), FORWARD);
+    assert!(runtime.set_fuel_budget(id, Some(1_000)).is_err());
+    let handled = runtime.call_handle(id, get("http://example.com/")).unwrap();
+    assert_eq!(handled.fuel_consumed, None);
+}
@@ -0,0 +1,139 @@
+mod common;
+
+use rama::http::HeaderValue;
+use runtime::{HandleError, RuntimeConfig};
+
+use common::{forwarded, get, header, start};
+
+/// Appends to `x-forwarded-for`, reports how many values it has in
+/// `x-forwarded-count`, replaces `via` and strips `authorization`.
+const EDIT_HEADERS: &str = r#"
+    (data (i32.const 0) "x-forwarded-for")
+    (data (i32.const 16) "10.0.0.2")
+    (data (i32.const 32) "authorization")
+    (data (i32.const 48) "x-forwarded-count")
+    (data (i32.const 80) "via")
+    (data (i32.const 96) "crossroads")
+    (func $run (param $request i32) (result i32)
+      (call $append_header (local.get $request)
+        (i32.const 0) (i32.const 15) (i32.const 16) (i32.const 8) (i32.const {ret}))
+      {try}
+      (call $remove_header (local.get $request) (i32.const 32) (i32.const 13) (i32.const {ret}))
+      {try}
+      (call $set_header (local.get $request)
+        (i32.const 80) (i32.const 3) (i32.const 96) (i32.const 10) (i32.const {ret}))
+      {try}
+      (call $get_header (local.get $request) (i32.const 0) (i32.const 15) (i32.const {ret}))
+      {try}
+      ;; The number of values as a single digit.
+      (i32.store8 (i32.const 72) (i32.add (i32.const 48) (i32.load (i32.const {ret+8}))))
+      (call $set_header (local.get $request)
+        (i32.const 48) (i32.const 17) (i32.const 72) (i32.const 1) (i32.const {ret}))
+      {try}
+      (call $continue))
+"#;
+
+/// Sets a header whose name contains a space.
+const INVALID_NAME: &str = r#"
+    (data (i32.const 0) "bad name")
+    (func $run (param $request i32) (result i32)
+      (call $set_header (local.get $request)
+        (i32.const 0) (i32.const 8) (i32.const 0) (i32.const 3) (i32.const {ret}))
+      {try}
+      (call $continue))
+"#;
+
+/// Copies the value of the only header, `x-latin-1`, into `x-listed` through
+/// `headers` and into `x-copy` through `get-header`.
+const COPY_OBS_TEXT: &str = r#"
+    (data (i32.const 0) "x-latin-1")
+    (data (i32.const 16) "x-copy")
+    (data (i32.const 32) "x-listed")
+    (func $run (param $request i32) (result i32)
+      (local $value i32)
+      (call $headers (local.get $request) (i32.const {ret}))
+      {try}
+      ;; Each entry is the name's pointer and length, then the value's.
+      (local.set $value (i32.load (i32.const {ret+4})))
+      (call $set_header (local.get $request) (i32.const 32) (i32.const 8)
+        (i32.load offset=8 (local.get $value)) (i32.load offset=12 (local.get $value))
+        (i32.const {ret}))
+      {try}
+      (call $get_header (local.get $request) (i32.const 0) (i32.const 9) (i32.const {ret}))
+      {try}
+      (local.set $value (i32.load (i32.const {ret+4})))
+      (call $set_header (local.get $request) (i32.const 16) (i32.const 6)
+        (i32.load (local.get $value)) (i32.load offset=4 (local.get $value)) (i32.const {ret}))
+      {try}
+      (call $continue))
+"#;
+
+/// Sets a header value containing a line feed.
+const INVALID_VALUE: &str = r#"
+    (data (i32.const 0) "x-bad")
+    (data (i32.const 16) "a\nb")
+    (func $run (param $request i32) (result i32)
+      (call $set_header (local.get $request)
+        (i32.const 0) (i32.const 5) (i32.const 16) (i32.const 3) (i32.const {ret}))
+      {try}
+      (call $continue))
+"#;
+
+#[test]
+fn obs_text_header_values_pass_through_as_bytes() {
+    let (runtime, id) = start(RuntimeConfig::default(), COPY_OBS_TEXT);
+    let mut request = get("http://example.com/");
+    request
+        .headers_mut()
+        .insert("x-latin-1", HeaderValue::from_bytes(b"caf\xe9").unwrap());
+    let request = forwarded(&runtime, id, request);
+    assert_eq!(header(&request, "x-listed"), b"caf\xe9");
+    assert_eq!(header(&request, "x-copy"), b"caf\xe9");
+}
+
+#[test]
+fn invalid_header_value_is_refused() {
+    let (runtime, id) = start(RuntimeConfig::default(), INVALID_VALUE);
+    let error = runtime
+        .call_handle(id, get("http://example.com/"))
+        .err()
+        .unwrap();
+    assert!(
+        matches!(error, HandleError::InvalidHeaderValue(_)),
+        "{error}"
+    );
+}
+
+#[test]
+fn headers_are_appended_replaced_and_removed() {
+    let (runtime, id) = start(RuntimeConfig::default(), EDIT_HEADERS);
+    let mut request = get("http://example.com/");
+    let headers = request.headers_mut();
+    headers.append("x-forwarded-for", "10.0.0.1".parse().unwrap());
+    headers.append("via", "1.1 edge".parse().unwrap());
+    headers.append("via", "1.1 cdn".parse().unwrap());
+    headers.append("authorization", "Bearer secret".parse().unwrap());
+    let request = forwarded(&runtime, id, request);
+    let forwarded_for = request
+        .headers()
+        .get_all("x-forwarded-for")
+        .iter()
+        .collect::<Vec<_>>();
+    assert_eq!(forwarded_for, ["10.0.0.1", "10.0.0.2"]);
+    assert_eq!(header(&request, "x-forwarded-count"), b"2");
+    assert_eq!(header(&request, "via"), b"crossroads");
+    assert!(!request.headers().contains_key("authorization"));
+}
+
+#[test]
+fn invalid_header_name_is_refused() {
+    let (runtime, id) = start(RuntimeConfig::default(), INVALID_NAME);
+    let error = runtime
+        .call_handle(id, get("http://example.com/"))
+        .err()
+        .unwrap();
+    assert!(
+        matches!(error, HandleError::InvalidHeaderName(_)),
+        "{error}"
+    );
+}
@@ -0,0 +1,203 @@
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use runtime::{HandleError, LoadError, Routed, Runtime, RuntimeConfig, Version, WasiConfig};
+
+use common::{channel, get, header, scratch_dir, tag_of, tagged};
+
+/// Writes the router tagged with the single character `tag` to `dir`.
+fn write_tagged(dir: &Path, tag: &str) -> PathBuf {
+    let path = dir.join(format!("router-{}.wasm", tag));
+    std::fs::write(&path, tagged(tag)).unwrap();
+    path
+}
+
+/// A component that exports a function instead of the router interface.
+const NOT_A_ROUTER: &str = r#"
+(component
+  (core module $M
+    (func (export "greet")))
+  (core instance $m (instantiate $M))
+  (func (export "greet") (canon lift (core func $m "greet"))))
+"#;
+
+#[test]
+fn component_without_router_export_is_refused_with_its_exports() {
+    let runtime = Runtime::new(RuntimeConfig::default()).unwrap();
+    let error = runtime
+        .add_instance_from_bytes(
+            "greeter",
+            Version::new(1, 0, 0),
+            NOT_A_ROUTER.as_bytes(),
+            WasiConfig::default(),
+        )
+        .err()
+        .unwrap();
+    let Some(LoadError::NotARouter {
+        name,
+        version,
+        imports,
+        exports,
+        ..
+    }) = error.downcast_ref()
+    else {
+        panic!("expected a LoadError, got {error:#}");
+    };
+    assert_eq!(
+        (name.as_str(), version),
+        ("greeter", &Version::new(1, 0, 0))
+    );
+    assert!(imports.is_empty());
+    assert_eq!(exports, &["greet"]);
+    assert!(runtime.components().is_empty());
+}
+
+#[test]
+fn router_of_another_interface_version_is_refused() {
+    let runtime = Runtime::new(RuntimeConfig::default()).unwrap();
+    let wat =
+        common::forward().replace("wit:crossroads/router@0.1.0", "wit:crossroads/router@0.2.0");
+    let error = runtime
+        .add_instance_from_bytes(
+            "router",
+            Version::new(2, 0, 0),
+            wat.as_bytes(),
+            WasiConfig::default(),
+        )
+        .err()
+        .unwrap();
+    let Some(LoadError::NotARouter { exports, .. }) = error.downcast_ref() else {
+        panic!("expected a LoadError, got {error:#}");
+    };
+    assert_eq!(exports, &["wit:crossroads/router@0.2.0"]);
+    let message = format!("{:#}", error);
+    assert!(
+        message.contains("Cannot use component router@2.0.0"),
+        "{message}"
+    );
+}
+
+#[test]
+fn removed_instance_is_unknown_and_its_id_is_not_reused() {
+    let dir = scratch_dir("instances-remove");
+    let runtime = Runtime::new(RuntimeConfig::default()).unwrap();
+    let version = Version::new(1, 0, 0);
+    let first = runtime
+        .add_instance(
+            "router",
+            version.clone(),
+            write_tagged(&dir, "a"),
+            WasiConfig::default(),
+        )
+        .unwrap();
+    runtime.remove_instance(first).unwrap();
+    let error = runtime
+        .call_handle(first, get("http://example.com/"))
+        .err()
+        .unwrap();
+    assert!(matches!(error, HandleError::UnknownInstance(_)), "{error}");
+    assert!(runtime.remove_instance(first).is_err());
+
+    // The name and version are free again, the id is not.
+    let second = runtime
+        .add_instance(
+            "router",
+            version,
+            write_tagged(&dir, "b"),
+            WasiConfig::default(),
+        )
+        .unwrap();
+    assert!(second > first);
+    assert_eq!(tag_of(&runtime, second), b"b");
+}
+
+#[test]
+fn replaced_instance_keeps_its_id_and_starts_its_stats_over() {
+    let dir = scratch_dir("instances-replace");
+    let runtime = Runtime::new(RuntimeConfig::default()).unwrap();
+    let id = runtime
+        .add_instance(
+            "router",
+            Version::new(1, 0, 0),
+            write_tagged(&dir, "a"),
+            WasiConfig::default(),
+        )
+        .unwrap();
+    assert_eq!(tag_of(&runtime, id), b"a");
+    runtime
+        .replace_instance(id, write_tagged(&dir, "b"))
+        .unwrap();
+    assert_eq!(tag_of(&runtime, id), b"b");
+    assert_eq!(runtime.stats(id).unwrap().calls, 1);
+    assert_eq!(runtime.resolve("router@1.0.0").unwrap(), id);
+}
+
+#[test]
+fn failed_replacement_keeps_the_old_component() {
+    let dir = scratch_dir("instances-replace-failed");
+    let runtime = Runtime::new(RuntimeConfig::default()).unwrap();
+    let id = runtime
+        .add_instance(
+            "router",
+            Version::new(1, 0, 0),
+            write_tagged(&dir, "a"),
+            WasiConfig::default(),
+        )
+        .unwrap();
+    let broken = dir.join("broken.wasm");
+    std::fs::write(&broken, "(component").unwrap();
+    assert!(runtime.replace_instance(id, &broken).is_err());
+    assert_eq!(tag_of(&runtime, id), b"a");
+}
+
+#[tokio::test]
+async fn calls_in_flight_finish_on_the_replaced_component() {
+    let dir = scratch_dir("instances-in-flight");
+    let runtime = Arc::new(
+        Runtime::new(RuntimeConfig {
+            async_support: true,
+            ..RuntimeConfig::default()
+        })
+        .unwrap(),
+    );
+    let id = runtime
+        .add_instance_async(
+            "router",
+            Version::new(1, 0, 0),
+            write_tagged(&dir, "a"),
+            WasiConfig::default(),
+        )
+        .await
+        .unwrap();
+    // The body only ends once the sender is dropped.
+    let (sender, body) = channel();
+    let mut request = get("http://example.com/");
+    *request.body_mut() = body;
+    let in_flight = tokio::spawn({
+        let runtime = runtime.clone();
+        async move { runtime.call_handle_async(id, request).await }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    runtime
+        .replace_instance_async(id, write_tagged(&dir, "b"))
+        .await
+        .unwrap();
+    drop(sender);
+    let Routed::Forward(request) = in_flight.await.unwrap().unwrap().routed else {
+        panic!("expected the request to be forwarded");
+    };
+    assert_eq!(header(&request, "x-router"), b"a");
+    let Routed::Forward(request) = runtime
+        .call_handle_async(id, get("http://example.com/"))
+        .await
+        .unwrap()
+        .routed
+    else {
+        panic!("expected the request to be forwarded");
+    };
+    assert_eq!(header(&request, "x-router"), b"b");
+}
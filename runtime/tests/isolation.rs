@@ -0,0 +1,107 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use runtime::{PoolingConfig, RuntimeConfig};
+
+use common::{forwarded, get, header, start};
+
+/// Counts its calls in memory and reports the count in `x-calls`.
+const COUNT_CALLS: &str = r#"
+    (data (i32.const 0) "x-calls")
+    (func $run (param $request i32) (result i32)
+      (i32.store (i32.const 200) (i32.add (i32.load (i32.const 200)) (i32.const 1)))
+      (i32.store8 (i32.const 16) (i32.add (i32.const 48) (i32.load (i32.const 200))))
+      (call $set_header (local.get $request)
+        (i32.const 0) (i32.const 7) (i32.const 16) (i32.const 1) (i32.const {ret}))
+      {try}
+      (call $continue))
+"#;
+
+fn calls_seen(config: RuntimeConfig) -> Vec<Vec<u8>> {
+    let (runtime, id) = start(config, COUNT_CALLS);
+    (0..3)
+        .map(|_| {
+            header(
+                &forwarded(&runtime, id, get("http://example.com/")),
+                "x-calls",
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn shared_router_keeps_its_state_between_calls() {
+    assert_eq!(calls_seen(RuntimeConfig::default()), [b"1", b"2", b"3"]);
+}
+
+#[test]
+fn isolated_calls_start_from_a_fresh_instance() {
+    let config = RuntimeConfig {
+        isolate_calls: true,
+        ..RuntimeConfig::default()
+    };
+    assert_eq!(calls_seen(config), [b"1", b"1", b"1"]);
+}
+
+#[test]
+fn isolated_calls_work_with_the_pooling_allocator() {
+    let config = RuntimeConfig {
+        isolate_calls: true,
+        pooling: Some(PoolingConfig {
+            max_instances: 4,
+            max_memory_size: 1 << 20,
+            ..PoolingConfig::default()
+        }),
+        ..RuntimeConfig::default()
+    };
+    assert_eq!(calls_seen(config), [b"1", b"1", b"1"]);
+}
+
+#[test]
+fn pooling_slots_of_isolated_calls_are_reused() {
+    // Two slots are enough for any number of calls, unless they leak.
+    let config = RuntimeConfig {
+        isolate_calls: true,
+        pooling: Some(PoolingConfig {
+            max_instances: 2,
+            max_memory_size: 1 << 20,
+            ..PoolingConfig::default()
+        }),
+        ..RuntimeConfig::default()
+    };
+    let (runtime, id) = start(config, COUNT_CALLS);
+    for _ in 0..2_000 {
+        let request = forwarded(&runtime, id, get("http://example.com/"));
+        assert_eq!(header(&request, "x-calls"), b"1");
+    }
+}
+
+/// Run with `cargo test --release -- --ignored` to check that the pooling
+/// allocator keeps fresh instances cheap.
+#[test]
+#[ignore = "timing depends on the machine, run it in release mode"]
+fn thousands_of_isolated_calls_per_second_with_the_pooling_allocator() {
+    const CALLS: u32 = 5_000;
+    let config = RuntimeConfig {
+        isolate_calls: true,
+        pooling: Some(PoolingConfig {
+            max_instances: 16,
+            max_memory_size: 1 << 20,
+            ..PoolingConfig::default()
+        }),
+        ..RuntimeConfig::default()
+    };
+    let (runtime, id) = start(config, COUNT_CALLS);
+    let started = Instant::now();
+    for _ in 0..CALLS {
+        runtime.call_handle(id, get("http://example.com/")).unwrap();
+    }
+    let elapsed = started.elapsed();
+    assert!(
+        elapsed < Duration::from_secs(5),
+        "{} fresh instances took {:?}",
+        CALLS,
+        elapsed
+    );
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use runtime::{HandleError, InstanceLimits, RuntimeConfig};
+
+use common::{get, start};
+
+/// Grows the memory by 16 pages, to 1088 KiB.
+const GROW: &str = r#"
+    (func $run (param $request i32) (result i32)
+      (if (i32.eq (memory.grow (i32.const 16)) (i32.const -1)) (then unreachable))
+      (call $continue))
+"#;
+
+fn limited() -> RuntimeConfig {
+    RuntimeConfig {
+        instance_limits: InstanceLimits {
+            max_memory_size: Some(1 << 20),
+            ..InstanceLimits::default()
+        },
+        ..RuntimeConfig::default()
+    }
+}
+
+#[test]
+fn growing_memory_beyond_the_limit_is_refused_and_counted() {
+    let (runtime, id) = start(limited(), GROW);
+    let error = runtime
+        .call_handle(id, get("http://example.com/"))
+        .err()
+        .unwrap();
+    assert!(matches!(error, HandleError::LimitExceeded(_)), "{error}");
+    let stats = runtime.stats(id).unwrap();
+    assert_eq!(stats.limit_violations, 1);
+    assert_eq!(stats.traps, 1);
+}
+
+#[test]
+fn raised_limits_apply_to_the_next_call() {
+    let (runtime, id) = start(limited(), GROW);
+    runtime
+        .set_limits(
+            id,
+            InstanceLimits {
+                max_memory_size: Some(2 << 20),
+                ..InstanceLimits::default()
+            },
+        )
+        .unwrap();
+    runtime.call_handle(id, get("http://example.com/")).unwrap();
+    assert_eq!(runtime.stats(id).unwrap().limit_violations, 0);
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use rama::http::dep::http_body::Frame;
+use rama::http::Request;
+use runtime::{HandleError, InstanceLimits, Runtime, RuntimeConfig};
+use tokio::sync::mpsc::Sender;
+
+use common::{READ_BODY, channel, get, start, start_async};
+
+const TRAP: &str = r#"
+    (func $run (param $request i32) (result i32)
+      unreachable)
+"#;
+
+/// A request whose body arrives through the returned sender.
+fn waiting() -> (Sender<Result<Frame<Bytes>, Infallible>>, Request) {
+    let (sender, body) = channel();
+    let mut request = get("http://example.com/");
+    *request.body_mut() = body;
+    (sender, request)
+}
+
+fn pooled(pool_size: usize) -> RuntimeConfig {
+    RuntimeConfig {
+        pool_size,
+        pool_timeout: Some(Duration::from_millis(100)),
+        ..RuntimeConfig::default()
+    }
+}
+
+#[test]
+fn runtime_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Runtime>();
+}
+
+#[tokio::test]
+async fn pool_handles_as_many_calls_concurrently_as_it_has_routers() {
+    let (runtime, id) = start_async(pooled(2), READ_BODY).await;
+    let runtime = Arc::new(runtime);
+    let (first_sender, first) = waiting();
+    let (second_sender, second) = waiting();
+    let calls = [first, second].map(|request| {
+        let runtime = runtime.clone();
+        tokio::spawn(async move { runtime.call_handle_async(id, request).await })
+    });
+    // Both calls wait for their bodies longer than the pool timeout, so with
+    // a single router the second one would fail.
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    drop((first_sender, second_sender));
+    for call in calls {
+        call.await.unwrap().unwrap();
+    }
+}
+
+#[tokio::test]
+async fn busy_pool_refuses_calls_after_the_pool_timeout() {
+    let (runtime, id) = start_async(pooled(1), READ_BODY).await;
+    let runtime = Arc::new(runtime);
+    let (sender, request) = waiting();
+    let busy = tokio::spawn({
+        let runtime = runtime.clone();
+        async move { runtime.call_handle_async(id, request).await }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let error = runtime
+        .call_handle_async(id, get("http://example.com/"))
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(error, HandleError::PoolExhausted), "{error}");
+
+    drop(sender);
+    busy.await.unwrap().unwrap();
+    runtime
+        .call_handle_async(id, get("http://example.com/"))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn router_of_a_dropped_call_is_replaced() {
+    let (runtime, id) = start_async(pooled(1), READ_BODY).await;
+    let guard = Arc::new(());
+    let (sender, mut request) = waiting();
+    request.extensions_mut().insert(guard.clone());
+    let call = runtime.call_handle_async(id, request);
+    assert!(
+        tokio::time::timeout(Duration::from_millis(20), call)
+            .await
+            .is_err()
+    );
+    // The request went down with the discarded router.
+    assert_eq!(Arc::strong_count(&guard), 1);
+    drop(sender);
+    runtime
+        .call_handle_async(id, get("http://example.com/"))
+        .await
+        .unwrap();
+}
+
+#[test]
+fn trap_is_reported_even_if_the_router_cannot_be_replaced() {
+    let (runtime, id) = start(pooled(1), TRAP);
+    // No memory fits, so no new router can be instantiated.
+    let no_memory = InstanceLimits {
+        max_memory_size: Some(0),
+        ..InstanceLimits::default()
+    };
+    runtime.set_limits(id, no_memory).unwrap();
+    let error = runtime
+        .call_handle(id, get("http://example.com/"))
+        .err()
+        .unwrap();
+    assert!(matches!(error, HandleError::Runtime(_)), "{error}");
+    let error = runtime
+        .call_handle(id, get("http://example.com/"))
+        .err()
+        .unwrap();
+    assert!(matches!(error, HandleError::LimitExceeded(_)), "{error}");
+
+    // The vacant router is instantiated once that is possible again.
+    runtime.set_limits(id, InstanceLimits::default()).unwrap();
+    let error = runtime
+        .call_handle(id, get("http://example.com/"))
+        .err()
+        .unwrap();
+    assert!(matches!(error, HandleError::Runtime(_)), "{error}");
+    assert_eq!(runtime.stats(id).unwrap().traps, 2);
+}
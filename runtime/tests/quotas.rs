@@ -0,0 +1,184 @@
+mod common;
+
+use runtime::{HandleError, RequestQuotas, RuntimeConfig};
+
+use common::{get, start};
+
+/// Sets a header on the request.
+const SET_HEADER: &str = r#"
+    (data (i32.const 0) "x-added")
+    (data (i32.const 16) "value")
+    (func $run (param $request i32) (result i32)
+      (call $set_header (local.get $request)
+        (i32.const 0) (i32.const 7) (i32.const 16) (i32.const 5) (i32.const {ret}))
+      {try}
+      (call $continue))
+"#;
+
+/// Moves the request to a long uri.
+const SET_URI: &str = r#"
+    (data (i32.const 0) "http://example.com/a/rather/long/path")
+    (func $run (param $request i32) (result i32)
+      (call $set_uri (local.get $request) (i32.const 0) (i32.const 37) (i32.const {ret}))
+      {try}
+      (call $continue))
+"#;
+
+/// Answers with a response that has one header.
+const RESPONSE_HEADER: &str = r#"
+    (data (i32.const 0) "x-added")
+    (data (i32.const 16) "value")
+    (func $run (param $request i32) (result i32)
+      (local $response i32)
+      (local.set $response (call $response_new))
+      (call $response_set_header (local.get $response)
+        (i32.const 0) (i32.const 7) (i32.const 16) (i32.const 5) (i32.const {ret}))
+      {try}
+      (call $respond (local.get $response)))
+"#;
+
+/// Answers with a response that has a 16 byte body.
+const RESPONSE_BODY: &str = r#"
+    (data (i32.const 0) "sixteen bytes...")
+    (func $run (param $request i32) (result i32)
+      (local $response i32)
+      (local.set $response (call $response_new))
+      (call $response_set_body (local.get $response) (i32.const 0) (i32.const 16) (i32.const {ret}))
+      {try}
+      (call $respond (local.get $response)))
+"#;
+
+/// Replaces the request body with 16 bytes.
+const REQUEST_BODY: &str = r#"
+    (data (i32.const 0) "sixteen bytes...")
+    (func $run (param $request i32) (result i32)
+      (call $set_body (local.get $request) (i32.const 0) (i32.const 16) (i32.const {ret}))
+      {try}
+      (call $continue))
+"#;
+
+/// Creates and drops two responses.
+const TWO_RESPONSES: &str = r#"
+    (func $run (param $request i32) (result i32)
+      (call $response_drop (call $response_new))
+      (call $response_drop (call $response_new))
+      (call $continue))
+"#;
+
+/// Opens and drops two body streams.
+const TWO_STREAMS: &str = r#"
+    (func $run (param $request i32) (result i32)
+      (call $body_stream (local.get $request) (i32.const {ret}))
+      {try}
+      (call $stream_drop (i32.load (i32.const {ret+4})))
+      (call $body_stream (local.get $request) (i32.const {ret}))
+      {try}
+      (call $stream_drop (i32.load (i32.const {ret+4})))
+      (call $continue))
+"#;
+
+fn with_quotas(quotas: RequestQuotas) -> RuntimeConfig {
+    RuntimeConfig {
+        request_quotas: quotas,
+        ..RuntimeConfig::default()
+    }
+}
+
+fn quota_error(quotas: RequestQuotas, core: &str) -> HandleError {
+    let (runtime, id) = start(with_quotas(quotas), core);
+    runtime
+        .call_handle(id, get("http://example.com/"))
+        .err()
+        .unwrap()
+}
+
+fn assert_quota_exceeded(error: HandleError) {
+    assert!(matches!(error, HandleError::QuotaExceeded(_)), "{error}");
+}
+
+#[test]
+fn request_headers_are_bounded() {
+    assert_quota_exceeded(quota_error(
+        RequestQuotas {
+            max_header_count: Some(0),
+            ..RequestQuotas::default()
+        },
+        SET_HEADER,
+    ));
+    assert_quota_exceeded(quota_error(
+        RequestQuotas {
+            max_header_bytes: Some(11),
+            ..RequestQuotas::default()
+        },
+        SET_HEADER,
+    ));
+}
+
+#[test]
+fn headers_within_the_quota_are_set() {
+    let (runtime, id) = start(
+        with_quotas(RequestQuotas {
+            max_header_count: Some(1),
+            max_header_bytes: Some(12),
+            ..RequestQuotas::default()
+        }),
+        SET_HEADER,
+    );
+    runtime.call_handle(id, get("http://example.com/")).unwrap();
+}
+
+#[test]
+fn uri_length_is_bounded() {
+    assert_quota_exceeded(quota_error(
+        RequestQuotas {
+            max_uri_length: Some(32),
+            ..RequestQuotas::default()
+        },
+        SET_URI,
+    ));
+}
+
+#[test]
+fn response_headers_are_bounded() {
+    assert_quota_exceeded(quota_error(
+        RequestQuotas {
+            max_header_count: Some(0),
+            ..RequestQuotas::default()
+        },
+        RESPONSE_HEADER,
+    ));
+}
+
+#[test]
+fn bodies_are_bounded() {
+    let quotas = RequestQuotas {
+        max_body_size: Some(15),
+        ..RequestQuotas::default()
+    };
+    assert_quota_exceeded(quota_error(quotas.clone(), RESPONSE_BODY));
+    assert_quota_exceeded(quota_error(quotas, REQUEST_BODY));
+}
+
+#[test]
+fn responses_and_streams_per_call_are_bounded() {
+    let quotas = RequestQuotas {
+        max_resources: Some(1),
+        ..RequestQuotas::default()
+    };
+    assert_quota_exceeded(quota_error(quotas.clone(), TWO_RESPONSES));
+    assert_quota_exceeded(quota_error(quotas, TWO_STREAMS));
+}
+
+#[test]
+fn resource_quota_applies_to_each_call() {
+    let (runtime, id) = start(
+        with_quotas(RequestQuotas {
+            max_resources: Some(2),
+            ..RequestQuotas::default()
+        }),
+        TWO_RESPONSES,
+    );
+    for _ in 0..3 {
+        runtime.call_handle(id, get("http://example.com/")).unwrap();
+    }
+}
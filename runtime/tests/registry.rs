@@ -0,0 +1,107 @@
+mod common;
+
+use runtime::{Runtime, RuntimeConfig, Version, WasiConfig};
+use sha2::{Digest, Sha256};
+
+use common::{forward, scratch_dir};
+
+fn add(runtime: &Runtime, name: &str, version: &str) -> anyhow::Result<usize> {
+    runtime.add_instance_from_bytes(
+        name,
+        version.parse().unwrap(),
+        forward().as_bytes(),
+        WasiConfig::default(),
+    )
+}
+
+#[test]
+fn references_resolve_to_the_matching_version() {
+    let runtime = Runtime::new(RuntimeConfig::default()).unwrap();
+    let old = add(&runtime, "router", "1.0.0").unwrap();
+    let new = add(&runtime, "router", "1.10.0").unwrap();
+    let between = add(&runtime, "router", "1.2.0").unwrap();
+
+    assert_eq!(runtime.resolve("router@1.0.0").unwrap(), old);
+    assert_eq!(runtime.resolve("router@1.2.0").unwrap(), between);
+    // Versions are ordered semantically, not as text.
+    assert_eq!(runtime.resolve("router@latest").unwrap(), new);
+    assert_eq!(runtime.resolve("router").unwrap(), new);
+
+    assert!(runtime.resolve("router@2.0.0").is_err());
+    assert!(runtime.resolve("router@one").is_err());
+    assert!(runtime.resolve("unknown").is_err());
+}
+
+#[test]
+fn latest_version_follows_removals() {
+    let runtime = Runtime::new(RuntimeConfig::default()).unwrap();
+    let old = add(&runtime, "router", "0.1.0").unwrap();
+    let new = add(&runtime, "router", "0.2.0").unwrap();
+    runtime.remove_instance(new).unwrap();
+    assert_eq!(runtime.resolve("router").unwrap(), old);
+    runtime.remove_instance(old).unwrap();
+    assert!(runtime.resolve("router").is_err());
+}
+
+#[test]
+fn same_name_and_version_is_refused() {
+    let runtime = Runtime::new(RuntimeConfig::default()).unwrap();
+    let id = add(&runtime, "router", "0.1.0").unwrap();
+    let error = add(&runtime, "router", "0.1.0").err().unwrap();
+    assert!(error.to_string().contains("already loaded"), "{error:#}");
+    assert_eq!(runtime.resolve("router@0.1.0").unwrap(), id);
+    assert_eq!(runtime.components().len(), 1);
+}
+
+#[test]
+fn names_that_cannot_be_referred_to_are_refused() {
+    let runtime = Runtime::new(RuntimeConfig::default()).unwrap();
+    for name in ["", "router@1.0.0", "@"] {
+        let error = add(&runtime, name, "1.0.0").err().unwrap();
+        assert!(error.to_string().contains("must not be empty"), "{error:#}");
+    }
+    assert!(runtime.components().is_empty());
+    assert!(runtime.resolve("router@1.0.0").is_err());
+}
+
+#[test]
+fn components_are_listed_with_their_path_and_hash() {
+    let dir = scratch_dir("registry-components");
+    let path = dir.join("router.wasm");
+    std::fs::write(&path, forward()).unwrap();
+    let runtime = Runtime::new(RuntimeConfig::default()).unwrap();
+    let from_file = runtime
+        .add_instance(
+            "router",
+            Version::new(0, 2, 0),
+            &path,
+            WasiConfig::default(),
+        )
+        .unwrap();
+    let from_bytes = add(&runtime, "router", "0.1.0").unwrap();
+    let other = add(&runtime, "api", "1.0.0").unwrap();
+
+    let components = runtime.components();
+    let listed = components
+        .iter()
+        .map(|component| {
+            (
+                component.id,
+                component.name.as_str(),
+                component.version.to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        listed,
+        [
+            (other, "api", "1.0.0".to_string()),
+            (from_bytes, "router", "0.1.0".to_string()),
+            (from_file, "router", "0.2.0".to_string()),
+        ]
+    );
+    assert_eq!(components[1].path, None);
+    assert_eq!(components[2].path.as_deref(), Some(path.as_path()));
+    let hash = format!("{:x}", Sha256::digest(forward()));
+    assert!(components.iter().all(|component| component.hash == hash));
+}
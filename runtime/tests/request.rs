@@ -0,0 +1,89 @@
+mod common;
+
+use rama::http::{Method, Version as HttpVersion};
+use runtime::{HandleError, RuntimeConfig};
+
+use common::{forwarded, get, header, start};
+
+/// Copies the method and version into the `x-method` and `x-version` headers.
+const REPORT_METHOD: &str = r#"
+    (data (i32.const 0) "x-method")
+    (data (i32.const 16) "x-version")
+    (func $run (param $request i32) (result i32)
+      (call $method (local.get $request) (i32.const {ret}))
+      {try}
+      (call $set_header (local.get $request) (i32.const 0) (i32.const 8)
+        (i32.load (i32.const {ret+4})) (i32.load (i32.const {ret+8})) (i32.const {ret}))
+      {try}
+      (call $version (local.get $request) (i32.const {ret}))
+      {try}
+      (call $set_header (local.get $request) (i32.const 16) (i32.const 9)
+        (i32.load (i32.const {ret+4})) (i32.load (i32.const {ret+8})) (i32.const {ret}))
+      {try}
+      (call $continue))
+"#;
+
+/// Sets the method to the first value of the `x-new-method` header.
+const SET_METHOD: &str = r#"
+    (data (i32.const 0) "x-new-method")
+    (func $run (param $request i32) (result i32)
+      (local $value i32)
+      (call $get_header (local.get $request) (i32.const 0) (i32.const 12) (i32.const {ret}))
+      {try}
+      (local.set $value (i32.load (i32.const {ret+4})))
+      (call $set_method (local.get $request)
+        (i32.load (local.get $value)) (i32.load offset=4 (local.get $value)) (i32.const {ret}))
+      {try}
+      (call $continue))
+"#;
+
+#[test]
+fn method_and_version_are_exposed() {
+    let (runtime, id) = start(RuntimeConfig::default(), REPORT_METHOD);
+    let mut request = get("http://example.com/");
+    *request.method_mut() = Method::DELETE;
+    *request.version_mut() = HttpVersion::HTTP_2;
+    let request = forwarded(&runtime, id, request);
+    assert_eq!(header(&request, "x-method"), b"DELETE");
+    assert_eq!(header(&request, "x-version"), b"HTTP/2.0");
+}
+
+#[test]
+fn every_version_has_a_fixed_name() {
+    let (runtime, id) = start(RuntimeConfig::default(), REPORT_METHOD);
+    let versions = [
+        (HttpVersion::HTTP_09, "HTTP/0.9"),
+        (HttpVersion::HTTP_10, "HTTP/1.0"),
+        (HttpVersion::HTTP_11, "HTTP/1.1"),
+        (HttpVersion::HTTP_2, "HTTP/2.0"),
+        (HttpVersion::HTTP_3, "HTTP/3.0"),
+    ];
+    for (version, name) in versions {
+        let mut request = get("http://example.com/");
+        *request.version_mut() = version;
+        let request = forwarded(&runtime, id, request);
+        assert_eq!(header(&request, "x-version"), name.as_bytes());
+    }
+}
+
+#[test]
+fn set_method_accepts_extension_methods() {
+    let (runtime, id) = start(RuntimeConfig::default(), SET_METHOD);
+    let mut request = get("http://example.com/");
+    request
+        .headers_mut()
+        .insert("x-new-method", "PURGE".parse().unwrap());
+    let request = forwarded(&runtime, id, request);
+    assert_eq!(request.method().as_str(), "PURGE");
+}
+
+#[test]
+fn set_method_rejects_invalid_tokens() {
+    let (runtime, id) = start(RuntimeConfig::default(), SET_METHOD);
+    let mut request = get("http://example.com/");
+    request
+        .headers_mut()
+        .insert("x-new-method", "GET /".parse().unwrap());
+    let error = runtime.call_handle(id, request).err().unwrap();
+    assert!(matches!(error, HandleError::InvalidMethod(_)), "{error}");
+}
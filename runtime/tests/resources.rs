@@ -0,0 +1,56 @@
+mod common;
+
+use std::sync::Arc;
+
+use runtime::{HandleError, RuntimeConfig};
+
+use common::{forwarded, get, start};
+
+/// Traps if the request has an `x-trap` header and forwards it otherwise.
+const TRAP_ON_HEADER: &str = r#"
+    (data (i32.const 0) "x-trap")
+    (func $run (param $request i32) (result i32)
+      (call $get_header (local.get $request) (i32.const 0) (i32.const 6) (i32.const {ret}))
+      {try}
+      (if (i32.load (i32.const {ret+8})) (then unreachable))
+      (call $continue))
+"#;
+
+const REJECT: &str = r#"
+    (func $run (param $request i32) (result i32)
+      (call $reject (i32.const 403)))
+"#;
+
+/// A request that holds `guard` for as long as it is alive.
+fn guarded(guard: &Arc<()>) -> rama::http::Request {
+    let mut request = get("http://example.com/");
+    request.extensions_mut().insert(guard.clone());
+    request
+}
+
+#[test]
+fn rejected_request_is_freed() {
+    let (runtime, id) = start(RuntimeConfig::default(), REJECT);
+    let guard = Arc::new(());
+    let error = runtime.call_handle(id, guarded(&guard)).err().unwrap();
+    assert!(matches!(error, HandleError::Rejected(_)), "{error}");
+    assert_eq!(Arc::strong_count(&guard), 1);
+}
+
+#[test]
+fn trapped_request_is_freed_and_the_router_replaced() {
+    let (runtime, id) = start(RuntimeConfig::default(), TRAP_ON_HEADER);
+    let guard = Arc::new(());
+    let mut request = guarded(&guard);
+    request.headers_mut().insert("x-trap", "1".parse().unwrap());
+    let error = runtime.call_handle(id, request).err().unwrap();
+    assert!(matches!(error, HandleError::Runtime(_)), "{error}");
+    assert_eq!(Arc::strong_count(&guard), 1);
+    assert_eq!(runtime.stats(id).unwrap().traps, 1);
+
+    // The only router of the pool was replaced and handles the next request.
+    let request = forwarded(&runtime, id, guarded(&guard));
+    assert_eq!(Arc::strong_count(&guard), 2);
+    drop(request);
+    assert_eq!(Arc::strong_count(&guard), 1);
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use futures::executor::block_on;
+use rama::http::StatusCode;
+use rama::http::dep::http_body_util::BodyExt;
+use runtime::{HandleError, RuntimeConfig};
+
+use common::{get, responded, start};
+
+/// Answers with a 403 that carries a header and a body.
+const FORBID: &str = r#"
+    (data (i32.const 0) "x-reason")
+    (data (i32.const 16) "tenant")
+    (data (i32.const 32) "not your tenant")
+    (func $run (param $request i32) (result i32)
+      (local $response i32)
+      (local.set $response (call $response_new))
+      (call $response_set_status (local.get $response) (i32.const 403) (i32.const {ret}))
+      {try}
+      (call $response_set_header (local.get $response)
+        (i32.const 0) (i32.const 8) (i32.const 16) (i32.const 6) (i32.const {ret}))
+      {try}
+      (call $response_set_body (local.get $response) (i32.const 32) (i32.const 15) (i32.const {ret}))
+      {try}
+      (call $respond (local.get $response)))
+"#;
+
+/// Tries to answer with a status outside of `100..=999`.
+const INVALID_STATUS: &str = r#"
+    (func $run (param $request i32) (result i32)
+      (local $response i32)
+      (local.set $response (call $response_new))
+      (call $response_set_status (local.get $response) (i32.const 1000) (i32.const {ret}))
+      {try}
+      (call $respond (local.get $response)))
+"#;
+
+#[test]
+fn router_answers_with_its_response() {
+    let (runtime, id) = start(RuntimeConfig::default(), FORBID);
+    let response = responded(&runtime, id, get("http://example.com/"));
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(response.headers()["x-reason"], "tenant");
+    let body = block_on(response.into_body().collect()).unwrap().to_bytes();
+    assert_eq!(body, "not your tenant");
+}
+
+#[test]
+fn invalid_status_is_refused() {
+    let (runtime, id) = start(RuntimeConfig::default(), INVALID_STATUS);
+    let error = runtime
+        .call_handle(id, get("http://example.com/"))
+        .err()
+        .unwrap();
+    assert!(matches!(error, HandleError::InvalidStatus(_)), "{error}");
+}
@@ -0,0 +1,181 @@
+mod common;
+
+use rama::http::{Request, header};
+use runtime::{HandleError, Runtime, RuntimeConfig};
+
+use common::{forwarded, get, header, start};
+
+/// Reports host, port, path and query in `x-host`, `x-port`, `x-path` and
+/// `x-query`. The uri must have all of them.
+const REPORT_URI: &str = r#"
+    (data (i32.const 0) "x-host")
+    (data (i32.const 16) "x-path")
+    (data (i32.const 32) "x-query")
+    (data (i32.const 48) "x-port")
+    (func $run (param $request i32) (result i32)
+      (local $port i32)
+      (local $at i32)
+      (call $host (local.get $request) (i32.const {ret}))
+      {try}
+      (call $set_header (local.get $request) (i32.const 0) (i32.const 6)
+        (i32.load (i32.const {ret+8})) (i32.load (i32.const {ret+12})) (i32.const {ret}))
+      {try}
+      (call $path (local.get $request) (i32.const {ret}))
+      {try}
+      (call $set_header (local.get $request) (i32.const 16) (i32.const 6)
+        (i32.load (i32.const {ret+4})) (i32.load (i32.const {ret+8})) (i32.const {ret}))
+      {try}
+      (call $query (local.get $request) (i32.const {ret}))
+      {try}
+      (call $set_header (local.get $request) (i32.const 32) (i32.const 7)
+        (i32.load (i32.const {ret+8})) (i32.load (i32.const {ret+12})) (i32.const {ret}))
+      {try}
+      (call $port (local.get $request) (i32.const {ret}))
+      {try}
+      ;; Writes the port in decimal, backwards from 86.
+      (local.set $port (i32.load16_u (i32.const {ret+6})))
+      (local.set $at (i32.const 86))
+      (loop $digits
+        (local.set $at (i32.sub (local.get $at) (i32.const 1)))
+        (i32.store8 (local.get $at)
+          (i32.add (i32.const 48) (i32.rem_u (local.get $port) (i32.const 10))))
+        (local.set $port (i32.div_u (local.get $port) (i32.const 10)))
+        (br_if $digits (local.get $port)))
+      (call $set_header (local.get $request) (i32.const 48) (i32.const 6)
+        (local.get $at) (i32.sub (i32.const 86) (local.get $at)) (i32.const {ret}))
+      {try}
+      (call $continue))
+"#;
+
+/// Calls the setter `$function` with the value of the `x-value` header.
+const SETTER: &str = r#"
+    (data (i32.const 0) "x-value")
+    (func $run (param $request i32) (result i32)
+      (local $value i32)
+      (call $get_header (local.get $request) (i32.const 0) (i32.const 7) (i32.const {ret}))
+      {try}
+      (local.set $value (i32.load (i32.const {ret+4})))
+      (call $function (local.get $request)
+        (i32.load (local.get $value)) (i32.load offset=4 (local.get $value)) (i32.const {ret}))
+      {try}
+      (call $continue))
+"#;
+
+/// Calls `$function` on `request` with `value`.
+fn set(function: &str, mut request: Request, value: &str) -> Result<Request, HandleError> {
+    let (runtime, id) = start(
+        RuntimeConfig::default(),
+        &SETTER.replace("$function", function),
+    );
+    request
+        .headers_mut()
+        .insert("x-value", value.parse().unwrap());
+    forward_or_fail(&runtime, id, request)
+}
+
+fn forward_or_fail(runtime: &Runtime, id: usize, request: Request) -> Result<Request, HandleError> {
+    match runtime.call_handle(id, request)?.routed {
+        runtime::Routed::Forward(request) => Ok(request),
+        runtime::Routed::Respond(_) => panic!("expected the request to be forwarded"),
+    }
+}
+
+/// A request in origin-form, as received by a server.
+fn origin_form(host: &str) -> Request {
+    let mut request = get("/api?page=2");
+    request
+        .headers_mut()
+        .insert(header::HOST, host.parse().unwrap());
+    request
+}
+
+#[test]
+fn uri_parts_are_exposed() {
+    let (runtime, id) = start(RuntimeConfig::default(), REPORT_URI);
+    let request = forwarded(
+        &runtime,
+        id,
+        get("https://user@example.com:8443/api/items?page=2"),
+    );
+    assert_eq!(header(&request, "x-host"), b"example.com");
+    assert_eq!(header(&request, "x-port"), b"8443");
+    assert_eq!(header(&request, "x-path"), b"/api/items");
+    assert_eq!(header(&request, "x-query"), b"page=2");
+}
+
+#[test]
+fn set_host_keeps_user_info_port_path_and_query() {
+    let request = set(
+        "$set_host",
+        get("https://user@example.com:8443/api?page=2"),
+        "upstream.internal",
+    )
+    .unwrap();
+    assert_eq!(
+        request.uri(),
+        "https://user@upstream.internal:8443/api?page=2"
+    );
+}
+
+#[test]
+fn set_host_refuses_user_info_ports_and_paths() {
+    for host in ["user@evil.com", "example.com:1", "example.com/x", "::1"] {
+        let error = set("$set_host", get("http://example.com:8080/"), host)
+            .err()
+            .unwrap();
+        assert!(matches!(error, HandleError::InvalidUri(_)), "{error}");
+    }
+}
+
+#[test]
+fn set_host_accepts_ipv6_literals() {
+    let request = set("$set_host", get("http://example.com:8080/"), "[::1]").unwrap();
+    assert_eq!(request.uri(), "http://[::1]:8080/");
+}
+
+#[test]
+fn set_host_on_origin_form_uses_http_and_updates_host_header() {
+    let request = set("$set_host", origin_form("example.com"), "upstream.internal").unwrap();
+    assert_eq!(request.uri(), "http://upstream.internal/api?page=2");
+    assert_eq!(request.headers()[header::HOST], "upstream.internal");
+}
+
+#[test]
+fn set_scheme_on_origin_form_takes_the_host_header() {
+    let request = set("$set_scheme", origin_form("example.com:8080"), "https").unwrap();
+    assert_eq!(request.uri(), "https://example.com:8080/api?page=2");
+}
+
+#[test]
+fn set_scheme_without_any_authority_is_refused() {
+    let error = set("$set_scheme", get("/api"), "https").err().unwrap();
+    assert!(matches!(error, HandleError::InvalidUri(_)), "{error}");
+}
+
+#[test]
+fn set_authority_updates_the_host_header() {
+    let request = set(
+        "$set_authority",
+        origin_form("example.com"),
+        "upstream.internal:9000",
+    )
+    .unwrap();
+    assert_eq!(request.uri(), "http://upstream.internal:9000/api?page=2");
+    assert_eq!(request.headers()[header::HOST], "upstream.internal:9000");
+}
+
+#[test]
+fn set_path_keeps_the_query() {
+    let request = set("$set_path", get("http://example.com/api?page=2"), "/v2/api").unwrap();
+    assert_eq!(request.uri(), "http://example.com/v2/api?page=2");
+}
+
+#[test]
+fn set_path_refuses_query_and_fragment() {
+    for path in ["/api?page=3", "/api#top", "@evil.com/x", "foo", ""] {
+        let error = set("$set_path", get("http://example.com/api?page=2"), path)
+            .err()
+            .unwrap();
+        assert!(matches!(error, HandleError::InvalidUri(_)), "{error}");
+    }
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use runtime::{Preopen, Runtime, RuntimeConfig, Version, WasiConfig};
+
+use common::{forwarded, get, header, router, scratch_dir};
+
+/// Reports how many environment variables, arguments and preopened
+/// directories it sees in `x-wasi`, one digit each.
+const COUNT_GRANTS: &str = r#"
+    (data (i32.const 0) "x-wasi")
+    (func $count (param $at i32)
+      (i32.store8 (local.get $at) (i32.add (i32.const 48) (i32.load (i32.const {ret+4})))))
+    (func $run (param $request i32) (result i32)
+      (call $get_environment (i32.const {ret}))
+      (call $count (i32.const 16))
+      (call $get_arguments (i32.const {ret}))
+      (call $count (i32.const 17))
+      (call $get_directories (i32.const {ret}))
+      (call $count (i32.const 18))
+      (call $set_header (local.get $request)
+        (i32.const 0) (i32.const 6) (i32.const 16) (i32.const 3) (i32.const {ret}))
+      {try}
+      (call $continue))
+"#;
+
+fn add(runtime: &Runtime, wasi: WasiConfig) -> anyhow::Result<usize> {
+    runtime.add_instance_from_bytes(
+        "router",
+        Version::new(0, 1, 0),
+        router(COUNT_GRANTS).as_bytes(),
+        wasi,
+    )
+}
+
+fn grants_seen(wasi: WasiConfig) -> Vec<u8> {
+    let runtime = Runtime::new(RuntimeConfig::default()).unwrap();
+    let id = add(&runtime, wasi).unwrap();
+    header(
+        &forwarded(&runtime, id, get("http://example.com/")),
+        "x-wasi",
+    )
+}
+
+#[test]
+fn router_sees_nothing_of_the_host_by_default() {
+    assert_eq!(grants_seen(WasiConfig::default()), b"000");
+}
+
+#[test]
+fn router_sees_only_what_it_was_granted() {
+    let dir = scratch_dir("wasi-grants");
+    let wasi = WasiConfig {
+        // Variables that are not set on the host are left out.
+        env: vec!["PATH".into(), "CROSSROADS_NOT_SET".into()],
+        args: vec!["router".into(), "--verbose".into()],
+        preopens: vec![Preopen {
+            host_path: dir,
+            guest_path: "/data".into(),
+        }],
+        ..WasiConfig::default()
+    };
+    assert_eq!(grants_seen(wasi), b"121");
+}
+
+#[test]
+fn missing_preopened_directory_is_refused() {
+    let dir = scratch_dir("wasi-missing").join("missing");
+    let runtime = Runtime::new(RuntimeConfig::default()).unwrap();
+    let wasi = WasiConfig {
+        preopens: vec![Preopen {
+            host_path: dir,
+            guest_path: "/data".into(),
+        }],
+        ..WasiConfig::default()
+    };
+    let error = add(&runtime, wasi).err().unwrap();
+    assert!(
+        format!("{:#}", error).contains("Cannot open directory"),
+        "{error:#}"
+    );
+}
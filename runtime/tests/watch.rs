@@ -0,0 +1,78 @@
+mod common;
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use runtime::{Runtime, RuntimeConfig, Version, WasiConfig};
+
+use common::{scratch_dir, tag_of, tagged};
+
+fn write_tagged(path: &Path, tag: &str) {
+    std::fs::write(path, tagged(tag)).unwrap();
+}
+
+/// Waits up to five seconds for the router `id` to be tagged `tag`.
+fn wait_for_tag(runtime: &Runtime, id: usize, tag: &str) -> bool {
+    let started = Instant::now();
+    while started.elapsed() < Duration::from_secs(5) {
+        if tag_of(runtime, id) == tag.as_bytes() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    false
+}
+
+#[test]
+fn changed_component_is_reloaded_and_broken_ones_are_skipped() {
+    let dir = scratch_dir("watch-reload");
+    let path = dir.join("router.wasm");
+    write_tagged(&path, "a");
+    let runtime = Arc::new(Runtime::new(RuntimeConfig::default()).unwrap());
+    let id = runtime
+        .add_instance(
+            "router",
+            Version::new(1, 0, 0),
+            &path,
+            WasiConfig::default(),
+        )
+        .unwrap();
+    runtime.watch(Duration::from_millis(10)).unwrap();
+    // A second watcher would reload every change twice.
+    assert!(runtime.watch(Duration::from_millis(10)).is_err());
+
+    std::thread::sleep(Duration::from_millis(20));
+    write_tagged(&path, "b");
+    assert!(wait_for_tag(&runtime, id, "b"));
+
+    // A broken build keeps the last working version running.
+    std::fs::write(&path, "(component").unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(tag_of(&runtime, id), b"b");
+
+    write_tagged(&path, "c");
+    assert!(wait_for_tag(&runtime, id, "c"));
+}
+
+#[test]
+fn replacing_with_a_precompiled_artifact_is_refused() {
+    let dir = scratch_dir("watch-precompiled");
+    let path = dir.join("router.wasm");
+    write_tagged(&path, "a");
+    let runtime = Runtime::new(RuntimeConfig::default()).unwrap();
+    let id = runtime
+        .add_instance(
+            "router",
+            Version::new(1, 0, 0),
+            &path,
+            WasiConfig::default(),
+        )
+        .unwrap();
+    let artifact = dir.join("router.cwasm");
+    std::fs::write(&artifact, runtime.precompile(&path).unwrap()).unwrap();
+
+    let error = runtime.replace_instance(id, &artifact).err().unwrap();
+    assert!(error.to_string().contains("add_precompiled"), "{error:#}");
+    assert_eq!(tag_of(&runtime, id), b"a");
+}